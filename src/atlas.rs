@@ -0,0 +1,168 @@
+use image::{Rgba, Rgba32FImage, RgbaImage, imageops};
+
+use crate::{color, config::Config, glyph, shape};
+
+/// Edge length of an atlas cell in pixels. Mirrored by `ATLAS_CELL` in
+/// `shader.wgsl`.
+pub const CELL_SIZE: u32 = 64;
+/// Transparent border inside every cell, so images don't touch the edge of
+/// their cell.
+pub const CELL_PADDING: u32 = 3;
+
+/// An image drawn on textured particles.
+pub struct Cell {
+    pub image: RgbaImage,
+    /// Whether the particle color is multiplied in. Off for images that have
+    /// their own colors, like color emoji.
+    pub tinted: bool,
+}
+
+/// Images for textured particles, packed into a grid of square cells.
+pub struct Atlas {
+    cells: Vec<Cell>,
+    /// Shape ids of the textured particles with their weights.
+    pub shapes: Vec<(u32, u32)>,
+}
+
+impl Atlas {
+    /// Rasterizes the glyphs and loads the sprites of the config.
+    pub fn new(config: &Config) -> Result<Self, String> {
+        let mut atlas = Self {
+            cells: Vec::new(),
+            shapes: Vec::new(),
+        };
+        let mut glyphs: Vec<char> = Vec::new();
+        let mut fonts = glyph::Fonts::new(config.glyph_font.as_deref())?;
+        for &(c, weight) in &config.glyphs {
+            let index = match glyphs.iter().position(|g| *g == c) {
+                Some(index) => index,
+                None => {
+                    atlas.cells.push(fonts.rasterize(c)?);
+                    glyphs.push(c);
+                    atlas.cells.len() - 1
+                }
+            };
+            atlas.shapes.push((atlas.shape(index), weight));
+        }
+        for sprite in &config.sprites {
+            // SVGs are rendered straight at the cell size
+            let image = sprite.load(CELL_SIZE - 2 * CELL_PADDING)?;
+            atlas.cells.push(Cell {
+                image: fit_to_cell(&image),
+                tinted: config.tint_sprites,
+            });
+            let shape = atlas.shape(atlas.cells.len() - 1);
+            atlas.shapes.push((shape, sprite.weight));
+        }
+        Ok(atlas)
+    }
+
+    /// Shape id of the cell at `index`, see `shape::TEXTURED`.
+    fn shape(&self, index: usize) -> u32 {
+        let tinted = if self.cells[index].tinted {
+            shape::TINTED
+        } else {
+            0
+        };
+        shape::TEXTURED | tinted | index as u32
+    }
+
+    /// The mip chain of the atlas image, premultiplied for the shader. Every
+    /// level keeps the grid layout and each cell is scaled down on its own,
+    /// so no level mixes texels of neighbouring cells. An empty atlas is a
+    /// single transparent pixel.
+    pub fn mip_levels(&self) -> Vec<RgbaImage> {
+        if self.cells.is_empty() {
+            return vec![RgbaImage::new(1, 1)];
+        }
+        let cells: Vec<_> = self.cells.iter().map(|c| premultiply(&c.image)).collect();
+        (0..=CELL_SIZE.ilog2())
+            .map(|level| {
+                let size = CELL_SIZE >> level;
+                let scaled: Vec<_> = cells
+                    .iter()
+                    .map(|cell| {
+                        let cell =
+                            imageops::resize(cell, size, size, imageops::FilterType::Triangle);
+                        encode_premultiplied(&cell)
+                    })
+                    .collect();
+                grid(&scaled, size)
+            })
+            .collect()
+    }
+}
+
+/// Lays cells `size` pixels wide out in a roughly square grid, the first cell
+/// in the top left corner.
+fn grid(cells: &[RgbaImage], size: u32) -> RgbaImage {
+    let columns = (cells.len() as f32).sqrt().ceil() as u32;
+    let rows = (cells.len() as u32).div_ceil(columns);
+    let mut image = RgbaImage::new(columns * size, rows * size);
+    for (i, cell) in cells.iter().enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        imageops::replace(
+            &mut image,
+            cell,
+            (column * size) as i64,
+            (row * size) as i64,
+        );
+    }
+    image
+}
+
+/// Scales an image to fit into a cell, keeping its aspect ratio, and centers
+/// it.
+pub fn fit_to_cell(image: &RgbaImage) -> RgbaImage {
+    let inner = (CELL_SIZE - 2 * CELL_PADDING) as f32;
+    let scale = inner / image.width().max(image.height()).max(1) as f32;
+    let width = ((image.width() as f32 * scale).round() as u32).clamp(1, inner as u32);
+    let height = ((image.height() as f32 * scale).round() as u32).clamp(1, inner as u32);
+    let scaled = imageops::resize(
+        &premultiply(image),
+        width,
+        height,
+        imageops::FilterType::Triangle,
+    );
+
+    let mut cell = RgbaImage::from_pixel(CELL_SIZE, CELL_SIZE, Rgba([0, 0, 0, 0]));
+    imageops::replace(
+        &mut cell,
+        &unpremultiply(&scaled),
+        ((CELL_SIZE - width) / 2) as i64,
+        ((CELL_SIZE - height) / 2) as i64,
+    );
+    cell
+}
+
+/// Converts straight alpha sRGB to linear light with premultiplied alpha,
+/// which filters without dark fringes around transparent texels.
+fn premultiply(image: &RgbaImage) -> Rgba32FImage {
+    Rgba32FImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0.map(|c| c as f32 / 255.0);
+        let [r, g, b] = [r, g, b].map(|c| color::srgb_to_linear(c) * a);
+        Rgba([r, g, b, a])
+    })
+}
+
+fn unpremultiply(image: &Rgba32FImage) -> RgbaImage {
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        let straight = |c: f32| if a > 0.0 { c / a } else { 0.0 };
+        to_rgba8([r, g, b].map(|c| color::linear_to_srgb(straight(c))), a)
+    })
+}
+
+/// Encodes premultiplied texels for an sRGB texture. The GPU decodes them
+/// back to premultiplied linear values.
+fn encode_premultiplied(image: &Rgba32FImage) -> RgbaImage {
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        to_rgba8([r, g, b].map(color::linear_to_srgb), a)
+    })
+}
+
+fn to_rgba8(rgb: [f32; 3], alpha: f32) -> Rgba<u8> {
+    let [r, g, b] = rgb;
+    Rgba([r, g, b, alpha].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+}
@@ -0,0 +1,339 @@
+use std::{path::PathBuf, str::FromStr};
+
+use crate::{
+    color,
+    config::{Layer, Profile},
+    edge::EdgeMode,
+    emitter::Preset,
+    glyph,
+    origin::Origin,
+    pile::PileClear,
+    shape,
+    sprite::Sprite,
+};
+
+pub const HELP: &str = "\
+Usage: confetti [OPTIONS]
+       confetti palettes [--png <FILE>]
+
+Display a confetti animation as an overlay on Wayland compositors.
+
+Commands:
+  palettes                 Print a swatch of every palette, or render them
+                           to a PNG image with --png <FILE>
+
+Options:
+  -p, --palette <NAME>     Color palette to use (default: retro), 'random' or
+                           'harmony:<color>:<rule>' with one of the rules
+                           complementary, analogous, triadic, tetradic,
+                           monochrome
+  -c, --colors <LIST>      Custom palette, e.g. '#ff0066:3,#0cf,hsl(45 100% 50%)'
+  -f, --palette-file <PATH>
+                           Import the palette from a pywal colors.json,
+                           base16/base24 scheme, Xresources or GIMP .gpl file
+      --filter-background  Drop imported colors close to the theme background
+  -i, --palette-from-image <FILE>
+                           Extract the palette from a PNG or JPEG image
+      --image-colors <N>   Number of colors to extract, up to 64 (default: 6)
+      --saturation-boost <FACTOR>
+                           Chroma multiplier for extracted colors (default: 1)
+      --color-variation <AMOUNT>
+                           Vary lightness and chroma per particle, 0 to 1
+                           (default: 0)
+  -d, --duration <SECS>    Seconds until the overlay closes (default: once all
+                           particles are gone, snow and rain run until stopped)
+  -n, --count <N>          Number of particles (default: 200)
+  -s, --size <SCALE>       Particle size multiplier, alias --scalar (default: 1)
+      --shapes <LIST>      Particle shapes with optional weights, e.g.
+                           'square:3,circle,star,heart'. Available: square,
+                           circle, triangle, star, heart (default: square)
+      --glyphs <LIST>      Characters or emoji drawn as particles with
+                           optional weights, e.g. '🎉:2,⭐,$,❤'. Mixed with
+                           --shapes if both are given
+      --glyph-font <PATH>  Font for --glyphs (default: picked by fontconfig)
+      --sprite <FILE>      PNG, JPEG or SVG image drawn as particles, with an
+                           optional weight like 'logo.svg:3'. Repeat for
+                           several images
+      --tint-sprites       Multiply sprites with the palette colors
+      --size-scale <RANGE> Random size multiplier per particle, as MIN..MAX
+                           or a single number (default: 1)
+      --rotation <RANGE>   Initial rotation in degrees (default: 0..360)
+      --angular-velocity <RANGE>
+                           Rotation speed in degrees per second (default: 0)
+      --spawn-delay <RANGE>
+                           Seconds each burst particle waits before it is
+                           launched (default: 0)
+      --lifetime <RANGE>   Lifetime of burst particles in multiples of
+                           --ticks (default: 1)
+      --opacity <RANGE>    Opacity per particle, 0 to 1 (default: 1)
+      --angle <DEGREES>    Launch direction, 90 is straight up (default: 90)
+      --spread <DEGREES>   Width of the launch cone (default: 45)
+      --start-velocity <PX>
+                           Initial speed in pixels per tick (default: 45)
+      --decay <FACTOR>     Speed kept after every tick (default: 0.9)
+  -g, --gravity <G>        Fall speed multiplier (default: 1)
+      --drift <PX>         Sideways movement per tick (default: 0)
+      --wind <X,Y>         Steady wind in pixels per tick, a single number
+                           blows sideways (default: 0,0)
+      --gusts <PX>         Peak speed of gusts that come and go in the
+                           direction of the wind (default: 0)
+      --turbulence <PX>    Speed of swirling air that makes particles drift
+                           and twirl (default: 0)
+      --turbulence-scale <PX>
+                           Size of the swirls (default: 150)
+      --edges <MODE>       What particles do at the left and right edges:
+                           clamp, bounce, wrap, or despawn once out of view
+                           (default: despawn)
+      --floor <MODE>       The same for the bottom edge, wrapped particles
+                           come back in at the top (default: despawn)
+      --restitution <FACTOR>
+                           Speed kept when bouncing off an edge (default: 0.6)
+      --pile               Let particles land and pile up on the bottom edge
+      --pile-timeout <SECS>
+                           Time from the first landing until the pile is
+                           cleared (default: 5)
+      --pile-clear <MODE>  How the pile is cleared: fade, or sweep it off to
+                           the right (default: fade)
+      --ticks <N>          Particle lifetime in ticks of 1/60s (default: 200)
+      --origin <X,Y>       Launch point as fractions of the screen or in
+                           pixels like '40px,-40px', where 0,0 is the top left
+                           and negative pixels count from the right or bottom.
+                           Also accepts top-left, top, top-right, left,
+                           center, right, bottom-left, bottom, bottom-right
+                           (default: center)
+      --preset <NAME>      Emitter arrangement: burst, cannons firing from
+                           both bottom corners, fireworks, or the continuous
+                           snow and rain (default: burst)
+      --waves <N>          Number of times the emitters fire (default: 1)
+      --wave-interval <SECS>
+                           Time between two waves (default: 0.4)
+      --spawn-rate <N>     Particles per second for snow and rain, --count
+                           limits how many are on screen (default: 40)
+      --ribbons <N>        Curly streamers launched with every burst
+                           (default: 0)
+      --seed <SEED>        Seed for reproducible bursts
+      --layer <LAYER>      Layer to draw on: background, bottom, top, overlay
+      --namespace <NAME>   Layer shell namespace for compositor rules
+  -P, --profile <NAME>     Use a named profile from the config file
+      --list-palettes      Print the available palettes and exit
+  -h, --help               Print this help and exit
+  -V, --version            Print the version and exit
+
+Settings are read from $XDG_CONFIG_HOME/confetti/config.toml first and
+overridden by the options given on the command line. Switches like --pile
+accept =false to turn off a setting from the config file.
+";
+
+/// What the user asked the program to do.
+pub enum Command {
+    Run,
+    Help,
+    Version,
+    ListPalettes,
+    /// Show swatches of all palettes, optionally rendered to a PNG image.
+    Palettes {
+        png: Option<PathBuf>,
+    },
+}
+
+/// Parsed command line arguments.
+pub struct Cli {
+    pub command: Command,
+    /// Named profile from the config file.
+    pub profile: Option<String>,
+    /// Settings given as flags; these take precedence over the config file.
+    pub overrides: Profile,
+}
+
+/// Parses the command line arguments, excluding the program name.
+pub fn parse<I>(args: I) -> Result<Cli, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut cli = Cli {
+        command: Command::Run,
+        profile: None,
+        overrides: Profile::default(),
+    };
+    let overrides = &mut cli.overrides;
+    let mut args = args.into_iter().peekable();
+    if args.next_if(|arg| arg == "palettes").is_some() {
+        cli.command = parse_palettes(args)?;
+        return Ok(cli);
+    }
+
+    while let Some(arg) = args.next() {
+        // Support both `--flag value` and `--flag=value`
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+        let mut value = || -> Result<String, String> {
+            match inline {
+                Some(value) => Ok(value.to_string()),
+                None => args
+                    .next()
+                    .ok_or_else(|| format!("Missing value for '{}'", flag)),
+            }
+        };
+        // Switches take an optional `=true` or `=false`, so they can turn off
+        // a setting from the config file
+        let switch = || match inline {
+            Some(value) => parse_value(&flag, value),
+            None => Ok(true),
+        };
+        let no_value = || match inline {
+            Some(_) => Err(format!("'{}' doesn't take a value", flag)),
+            None => Ok(()),
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => {
+                no_value()?;
+                cli.command = Command::Help
+            }
+            "-V" | "--version" => {
+                no_value()?;
+                cli.command = Command::Version
+            }
+            "--list-palettes" => {
+                no_value()?;
+                cli.command = Command::ListPalettes
+            }
+            "-P" | "--profile" => cli.profile = Some(value()?),
+            // `--pallette` was accepted by earlier releases
+            "-p" | "--palette" | "--pallette" => overrides.palette = Some(value()?),
+            "-c" | "--colors" => {
+                overrides.colors = Some(check_list(&flag, value()?, color::parse_color_list)?)
+            }
+            "-f" | "--palette-file" => overrides.palette_file = Some(value()?.into()),
+            "--filter-background" => overrides.filter_background = Some(switch()?),
+            "-i" | "--palette-from-image" => overrides.palette_image = Some(value()?.into()),
+            "--image-colors" => overrides.image_colors = Some(parse_value(&flag, &value()?)?),
+            "--saturation-boost" => {
+                overrides.saturation_boost = Some(parse_value(&flag, &value()?)?)
+            }
+            "--color-variation" => overrides.color_variation = Some(parse_value(&flag, &value()?)?),
+            "-d" | "--duration" => overrides.duration = Some(parse_value(&flag, &value()?)?),
+            "-n" | "--count" => overrides.count = Some(parse_value(&flag, &value()?)?),
+            "-s" | "--size" | "--scalar" => overrides.size = Some(parse_value(&flag, &value()?)?),
+            "--shapes" => {
+                overrides.shapes = Some(check_list(&flag, value()?, shape::parse_shape_list)?)
+            }
+            "--glyphs" => {
+                overrides.glyphs = Some(check_list(&flag, value()?, glyph::parse_glyph_list)?)
+            }
+            "--glyph-font" => overrides.glyph_font = Some(value()?.into()),
+            "--sprite" => {
+                let sprite = parse_spec::<Sprite>(&flag, &value()?)?;
+                overrides.sprites.get_or_insert_default().push(sprite);
+            }
+            "--tint-sprites" => overrides.tint_sprites = Some(switch()?),
+            "--size-scale" => overrides.size_scale = Some(parse_spec(&flag, &value()?)?),
+            "--rotation" => overrides.rotation = Some(parse_spec(&flag, &value()?)?),
+            "--angular-velocity" => {
+                overrides.angular_velocity = Some(parse_spec(&flag, &value()?)?)
+            }
+            "--spawn-delay" => overrides.spawn_delay = Some(parse_spec(&flag, &value()?)?),
+            "--lifetime" => overrides.lifetime = Some(parse_spec(&flag, &value()?)?),
+            "--opacity" => overrides.opacity = Some(parse_spec(&flag, &value()?)?),
+            "--angle" => overrides.angle = Some(parse_value(&flag, &value()?)?),
+            "--spread" => overrides.spread = Some(parse_value(&flag, &value()?)?),
+            "--start-velocity" => overrides.start_velocity = Some(parse_value(&flag, &value()?)?),
+            "--decay" => overrides.decay = Some(parse_value(&flag, &value()?)?),
+            "-g" | "--gravity" => overrides.gravity = Some(parse_value(&flag, &value()?)?),
+            "--drift" => overrides.drift = Some(parse_value(&flag, &value()?)?),
+            "--wind" => overrides.wind = Some(parse_vector(&flag, &value()?)?),
+            "--gusts" => overrides.gusts = Some(parse_value(&flag, &value()?)?),
+            "--turbulence" => overrides.turbulence = Some(parse_value(&flag, &value()?)?),
+            "--turbulence-scale" => {
+                overrides.turbulence_scale = Some(parse_value(&flag, &value()?)?)
+            }
+            "--edges" => overrides.edges = Some(parse_spec::<EdgeMode>(&flag, &value()?)?),
+            "--floor" => overrides.floor = Some(parse_spec::<EdgeMode>(&flag, &value()?)?),
+            "--restitution" => overrides.restitution = Some(parse_value(&flag, &value()?)?),
+            "--pile" => overrides.pile = Some(switch()?),
+            "--pile-timeout" => overrides.pile_timeout = Some(parse_value(&flag, &value()?)?),
+            "--pile-clear" => {
+                overrides.pile_clear = Some(parse_spec::<PileClear>(&flag, &value()?)?)
+            }
+            "--ticks" => overrides.ticks = Some(parse_value(&flag, &value()?)?),
+            "--origin" => overrides.origin = Some(parse_spec::<Origin>(&flag, &value()?)?),
+            "--preset" => overrides.preset = Some(parse_spec::<Preset>(&flag, &value()?)?),
+            "--waves" => overrides.waves = Some(parse_value(&flag, &value()?)?),
+            "--wave-interval" => overrides.wave_interval = Some(parse_value(&flag, &value()?)?),
+            "--spawn-rate" => overrides.spawn_rate = Some(parse_value(&flag, &value()?)?),
+            "--ribbons" => overrides.ribbons = Some(parse_value(&flag, &value()?)?),
+            "--seed" => overrides.seed = Some(parse_value(&flag, &value()?)?),
+            "--layer" => overrides.layer = Some(parse_spec::<Layer>(&flag, &value()?)?),
+            "--namespace" => overrides.namespace = Some(value()?),
+            _ => return Err(format!("Unknown argument '{}'", arg)),
+        }
+
+        if !matches!(cli.command, Command::Run) {
+            break;
+        }
+    }
+
+    Ok(cli)
+}
+
+/// The command line flag for a config key.
+pub fn flag(key: &str) -> String {
+    match key {
+        "palette_image" => "--palette-from-image".to_string(),
+        _ => format!("--{}", key.replace('_', "-")),
+    }
+}
+
+/// Parses the options of the `palettes` command.
+fn parse_palettes(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut png = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--png" => {
+                let path = args.next().ok_or("Missing value for '--png'")?;
+                png = Some(path.into());
+            }
+            _ => match arg.strip_prefix("--png=") {
+                Some(path) => png = Some(path.into()),
+                None => return Err(format!("Unknown argument '{}' for 'palettes'", arg)),
+            },
+        }
+    }
+    Ok(Command::Palettes { png })
+}
+
+/// Parses a value like a `MIN..MAX` range or a mode name, keeping the reason
+/// it was rejected.
+fn parse_spec<T: FromStr<Err = String>>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|e| format!("Invalid value '{}' for '{}': {}", value, flag, e))
+}
+
+/// Checks a list that is only parsed once all settings are resolved, so its
+/// errors still name the flag.
+fn check_list<T>(
+    flag: &str,
+    value: String,
+    parse: fn(&str) -> Result<T, String>,
+) -> Result<String, String> {
+    parse(&value).map_err(|e| format!("Invalid value '{}' for '{}': {}", value, flag, e))?;
+    Ok(value)
+}
+
+/// Parses `X,Y`, or a single number as `X,0`.
+fn parse_vector(flag: &str, value: &str) -> Result<[f32; 2], String> {
+    match value.split_once(',') {
+        Some((x, y)) => Ok([parse_value(flag, x.trim())?, parse_value(flag, y.trim())?]),
+        None => Ok([parse_value(flag, value.trim())?, 0.0]),
+    }
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("Invalid value '{}' for '{}'", value, flag))
+}
@@ -0,0 +1,368 @@
+use crate::{color_palette::WeightedColors, weighted};
+
+/// Parses a comma separated list of colors with optional weights, e.g.
+/// `#ff0066:3, #0cf, hsl(45 100% 50%)`.
+pub fn parse_color_list(list: &str) -> Result<WeightedColors, String> {
+    let mut colors = WeightedColors::new();
+    for token in split_top_level(list) {
+        let token = token.trim();
+        if token.is_empty() {
+            continue;
+        }
+        colors.push(parse_weighted_color(token)?);
+    }
+    if colors.is_empty() {
+        return Err(format!("No colors in '{}'", list));
+    }
+    Ok(colors)
+}
+
+/// Parses a single color with an optional `:weight` suffix.
+pub fn parse_weighted_color(token: &str) -> Result<([f32; 3], u32), String> {
+    let (color, weight) = weighted::split_weight(token)?;
+    Ok((parse_color(color)?, weight))
+}
+
+/// Parses a CSS style color: `#rgb`, `#rrggbb`, `rgb(...)` or `hsl(...)`.
+/// The alpha forms (`#rrggbbaa`, `rgba(...)`, `hsla(...)`) are accepted as
+/// well, their alpha is ignored.
+pub fn parse_color(s: &str) -> Result<[f32; 3], String> {
+    let s = s.trim();
+    let lower = s.to_lowercase();
+    if let Some(hex) = lower.strip_prefix('#') {
+        return parse_hex(hex).ok_or_else(|| {
+            format!(
+                "Invalid hex color '{}': expected #rgb or #rrggbb with hex digits",
+                s
+            )
+        });
+    }
+    if let Some(args) = function_args(&lower, "rgb").or_else(|| function_args(&lower, "rgba")) {
+        return parse_rgb(without_alpha(&args))
+            .map_err(|e| format!("Invalid color '{}': {}", s, e));
+    }
+    if let Some(args) = function_args(&lower, "hsl").or_else(|| function_args(&lower, "hsla")) {
+        return parse_hsl(without_alpha(&args))
+            .map_err(|e| format!("Invalid color '{}': {}", s, e));
+    }
+    Err(format!(
+        "Invalid color '{}': expected #rrggbb, rgb(r g b) or hsl(h s% l%)",
+        s
+    ))
+}
+
+fn parse_hex(hex: &str) -> Option<[f32; 3]> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digits = match hex.len() {
+        3 | 4 => hex.chars().flat_map(|c| [c, c]).collect::<String>(),
+        6 | 8 => hex.to_string(),
+        _ => return None,
+    };
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+    Some([
+        channel(0)? as f32 / 255.0,
+        channel(2)? as f32 / 255.0,
+        channel(4)? as f32 / 255.0,
+    ])
+}
+
+/// Returns the arguments of `name(...)`, accepting both comma and space
+/// separated forms.
+fn function_args(s: &str, name: &str) -> Option<Vec<String>> {
+    let inner = s.strip_prefix(name)?.trim_start().strip_prefix('(')?;
+    let inner = inner.strip_suffix(')')?;
+    Some(
+        inner
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|arg| !arg.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Drops the alpha of `r, g, b, a` and `r g b / a` argument lists.
+fn without_alpha(args: &[String]) -> &[String] {
+    match args {
+        [color @ .., slash, _] if slash == "/" => color,
+        [color @ .., _] if args.len() == 4 => color,
+        _ => args,
+    }
+}
+
+fn parse_rgb(args: &[String]) -> Result<[f32; 3], String> {
+    let [r, g, b] = args else {
+        return Err(format!("expected 3 channels, found {}", args.len()));
+    };
+    let channel = |arg: &str| -> Result<f32, String> {
+        let value = match arg.strip_suffix('%') {
+            Some(percent) => parse_number(percent)? / 100.0,
+            None => parse_number(arg)? / 255.0,
+        };
+        in_unit_range(arg, value)
+    };
+    Ok([channel(r)?, channel(g)?, channel(b)?])
+}
+
+fn parse_hsl(args: &[String]) -> Result<[f32; 3], String> {
+    let [h, s, l] = args else {
+        return Err(format!("expected 3 components, found {}", args.len()));
+    };
+    let hue = if let Some(turn) = h.strip_suffix("turn") {
+        parse_number(turn)? * 360.0
+    } else if let Some(rad) = h.strip_suffix("rad") {
+        parse_number(rad)?.to_degrees()
+    } else {
+        parse_number(h.strip_suffix("deg").unwrap_or(h))?
+    };
+    let percent = |arg: &str| -> Result<f32, String> {
+        let value = arg
+            .strip_suffix('%')
+            .ok_or_else(|| format!("expected a percentage, found '{}'", arg))?;
+        in_unit_range(arg, parse_number(value)? / 100.0)
+    };
+    Ok(hsl_to_rgb(hue, percent(s)?, percent(l)?))
+}
+
+fn parse_number(s: &str) -> Result<f32, String> {
+    s.parse::<f32>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| format!("'{}' is not a number", s))
+}
+
+fn in_unit_range(arg: &str, value: f32) -> Result<f32, String> {
+    if !(0.0..=1.0).contains(&value) {
+        return Err(format!("'{}' is out of range", arg));
+    }
+    Ok(value)
+}
+
+/// Converts hue in degrees and saturation and lightness in `0.0..=1.0` to RGB.
+pub fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> [f32; 3] {
+    let hue = hue.rem_euclid(360.0);
+    let a = saturation * lightness.min(1.0 - lightness);
+    let f = |n: f32| {
+        let k = (n + hue / 30.0) % 12.0;
+        lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    [f(0.0), f(8.0), f(4.0)]
+}
+
+/// Splits on commas that are not inside parentheses.
+fn split_top_level(list: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in list.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&list[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&list[start..]);
+    parts
+}
+
+/// Converts a gamma encoded sRGB channel to linear light.
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear light channel to gamma encoded sRGB.
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Converts an sRGB color to OKLab.
+pub fn srgb_to_oklab(rgb: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(srgb_to_linear);
+    let l = (0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+    [
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    ]
+}
+
+/// Converts an OKLab color to sRGB, clamping colors outside of the gamut.
+pub fn oklab_to_srgb(lab: [f32; 3]) -> [f32; 3] {
+    oklab_to_linear(lab).map(|c| linear_to_srgb(c.clamp(0.0, 1.0)))
+}
+
+fn oklab_to_linear(lab: [f32; 3]) -> [f32; 3] {
+    let [l, a, b] = lab;
+    let l_ = (l + 0.396_337_78 * a + 0.215_803_76 * b).powi(3);
+    let m_ = (l - 0.105_561_346 * a - 0.063_854_17 * b).powi(3);
+    let s_ = (l - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+    [
+        4.076_741_7 * l_ - 3.307_711_6 * m_ + 0.230_969_94 * s_,
+        -1.268_438 * l_ + 2.609_757_4 * m_ - 0.341_319_38 * s_,
+        -0.004_196_086_3 * l_ - 0.703_418_6 * m_ + 1.707_614_7 * s_,
+    ]
+}
+
+/// Converts OKLCH to sRGB. Colors outside of the gamut have their chroma
+/// reduced, which keeps lightness and hue intact.
+pub fn oklch_to_srgb(lch: [f32; 3]) -> [f32; 3] {
+    let [l, c, h] = lch;
+    let in_gamut = |c: f32| {
+        oklab_to_linear(oklch_to_oklab([l, c, h]))
+            .iter()
+            .all(|v| (-1e-4..=1.0 + 1e-4).contains(v))
+    };
+    let (mut low, mut high) = (0.0, c);
+    if !in_gamut(high) {
+        for _ in 0..16 {
+            let mid = (low + high) / 2.0;
+            if in_gamut(mid) {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        high = low;
+    }
+    oklab_to_srgb(oklch_to_oklab([l, high, h]))
+}
+
+/// Converts OKLab to lightness, chroma and hue in degrees.
+pub fn oklab_to_oklch(lab: [f32; 3]) -> [f32; 3] {
+    let [l, a, b] = lab;
+    [l, a.hypot(b), b.atan2(a).to_degrees().rem_euclid(360.0)]
+}
+
+/// Converts OKLCH with the hue in degrees to OKLab.
+pub fn oklch_to_oklab(lch: [f32; 3]) -> [f32; 3] {
+    let [l, c, h] = lch;
+    let (sin, cos) = h.to_radians().sin_cos();
+    [l, c * cos, c * sin]
+}
+
+/// Perceptual distance between two sRGB colors.
+pub fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let a = srgb_to_oklab(a);
+    let b = srgb_to_oklab(b);
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+        let close = actual
+            .iter()
+            .zip(expected)
+            .all(|(a, e)| (a - e).abs() < 1e-4);
+        assert!(close, "{:?} is not {:?}", actual, expected);
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        let pink = [1.0, 0.0, 0.4];
+        for hex in [
+            "#ff0066",
+            "#FF0066",
+            "#f06",
+            "#f06f",
+            "#ff006680",
+            " #ff0066 ",
+        ] {
+            assert_close(parse_color(hex).unwrap(), pink);
+        }
+    }
+
+    #[test]
+    fn parses_color_functions() {
+        let pink = [1.0, 0.0, 0.4];
+        for color in [
+            "rgb(255 0 102)",
+            "rgb(255, 0, 102)",
+            "RGB(100% 0% 40%)",
+            "rgb(255 0 102 / 50%)",
+            "rgba(255, 0, 102, 0.5)",
+        ] {
+            assert_close(parse_color(color).unwrap(), pink);
+        }
+        assert_close(parse_color("hsl(0 100% 50%)").unwrap(), [1.0, 0.0, 0.0]);
+        assert_close(
+            parse_color("hsl(120deg, 100%, 50%)").unwrap(),
+            [0.0, 1.0, 0.0],
+        );
+        assert_close(
+            parse_color("hsl(0.5turn 100% 50%)").unwrap(),
+            [0.0, 1.0, 1.0],
+        );
+        assert_close(
+            parse_color("hsla(240 100% 50% / 1)").unwrap(),
+            [0.0, 0.0, 1.0],
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_colors() {
+        let error = |s: &str| parse_color(s).unwrap_err();
+        assert_eq!(
+            error("#ff00zz"),
+            "Invalid hex color '#ff00zz': expected #rgb or #rrggbb with hex digits"
+        );
+        assert_eq!(
+            error("#ff00f"),
+            "Invalid hex color '#ff00f': expected #rgb or #rrggbb with hex digits"
+        );
+        assert_eq!(
+            error("rgb(1 2)"),
+            "Invalid color 'rgb(1 2)': expected 3 channels, found 2"
+        );
+        assert_eq!(
+            error("rgb(300 0 0)"),
+            "Invalid color 'rgb(300 0 0)': '300' is out of range"
+        );
+        assert_eq!(
+            error("rgb(a 0 0)"),
+            "Invalid color 'rgb(a 0 0)': 'a' is not a number"
+        );
+        assert_eq!(
+            error("hsl(0 50 50%)"),
+            "Invalid color 'hsl(0 50 50%)': expected a percentage, found '50'"
+        );
+        assert_eq!(
+            error("red"),
+            "Invalid color 'red': expected #rrggbb, rgb(r g b) or hsl(h s% l%)"
+        );
+    }
+
+    #[test]
+    fn parses_weighted_lists() {
+        let colors = parse_color_list("#ff0066:3, rgb(0, 204, 255), hsl(45 100% 50%):2,").unwrap();
+        let weights = colors.iter().map(|(_, w)| *w).collect::<Vec<_>>();
+        assert_eq!(weights, [3, 1, 2]);
+        assert_close(colors[1].0, [0.0, 0.8, 1.0]);
+        assert_eq!(parse_color_list(" , ").unwrap_err(), "No colors in ' , '");
+        assert_eq!(
+            parse_color_list("#fff, #000:0").unwrap_err(),
+            "Invalid weight '0' in '#000:0': expected a whole number from 1 to 1000"
+        );
+    }
+}
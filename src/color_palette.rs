@@ -1,3 +1,36 @@
+use std::{collections::HashMap, path::Path, sync::OnceLock};
+
+use rand::Rng;
+
+use crate::{
+    color,
+    harmony::{self, HarmonyRule},
+    palette_file, palette_image, sherlock_theme,
+};
+
+/// Palettes defined in the config file, keyed by lowercase name.
+static USER_PALETTES: OnceLock<HashMap<String, WeightedColors>> = OnceLock::new();
+
+/// Makes the palettes from the config file available to `from_str`.
+pub fn register_user_palettes(palettes: &HashMap<String, WeightedColors>) {
+    let palettes = palettes
+        .iter()
+        .map(|(name, colors)| (name.to_lowercase(), colors.clone()))
+        .collect();
+    let _ = USER_PALETTES.set(palettes);
+}
+
+/// Sorted names of the palettes defined in the config file.
+pub fn user_palette_names() -> Vec<&'static str> {
+    let mut names = USER_PALETTES
+        .get()
+        .map(|palettes| palettes.keys().map(String::as_str).collect::<Vec<_>>())
+        .unwrap_or_default();
+    names.sort_unstable();
+    names
+}
+
+#[derive(Debug, Clone, Default)]
 pub enum ColorPalette {
     Party,
     Pastel,
@@ -6,14 +39,60 @@ pub enum ColorPalette {
     Cool,
     Sunset,
     Ocean,
+    #[default]
     Retro,
     Forest,
     Candy,
+    /// Derived from the Sherlock launcher theme, falls back to `Retro`.
+    Sherlock,
+    /// Generated around a base color, written as `harmony:<color>:<rule>`.
+    Harmony {
+        base: [f32; 3],
+        rule: HarmonyRule,
+    },
+    /// A freshly generated palette on every run.
+    Random,
+    Custom(WeightedColors),
 }
 
-type ColorVec = Vec<[f32; 3]>;
+/// Palette colors as gamma encoded sRGB in the range `0.0..=1.0`.
+pub type ColorVec = Vec<[f32; 3]>;
+/// Palette colors with how often each is picked relative to the others.
+pub type WeightedColors = Vec<([f32; 3], u32)>;
 impl ColorPalette {
-    pub fn get_colors(&self) -> Vec<[f32; 3]> {
+    /// Names accepted by `from_str`, in display order.
+    pub const NAMES: [&'static str; 12] = [
+        "party", "pastel", "earth", "neon", "cool", "sunset", "ocean", "retro", "forest", "candy",
+        "sherlock", "random",
+    ];
+
+    /// Imports a palette from a theme file, see `palette_file::load`.
+    pub fn from_file(path: &Path, filter_background: bool) -> Result<Self, String> {
+        palette_file::load(path, filter_background).map(Self::unweighted)
+    }
+
+    /// Extracts a palette from the dominant colors of an image, see
+    /// `palette_image::load`.
+    pub fn from_image(path: &Path, count: usize, saturation_boost: f32) -> Result<Self, String> {
+        palette_image::load(path, count, saturation_boost).map(Self::unweighted)
+    }
+
+    fn unweighted(colors: ColorVec) -> Self {
+        Self::Custom(colors.into_iter().map(|c| (c, 1)).collect())
+    }
+
+    /// The colors with their weights, for picking particle colors. A random
+    /// palette is generated with `rng`, so it follows the seed.
+    pub fn weighted_colors(&self, rng: &mut impl Rng) -> WeightedColors {
+        match self {
+            Self::Custom(colors) => colors.clone(),
+            Self::Random => harmony::random(rng).into_iter().map(|c| (c, 1)).collect(),
+            _ => self.get_colors().into_iter().map(|c| (c, 1)).collect(),
+        }
+    }
+
+    /// The colors of the palette, each once.
+    pub fn get_colors(&self) -> ColorVec {
         match &self {
             Self::Party => Self::party(),
             Self::Pastel => Self::pastel(),
@@ -25,6 +104,10 @@ impl ColorPalette {
             Self::Retro => Self::retro(),
             Self::Forest => Self::forest(),
             Self::Candy => Self::candy(),
+            Self::Sherlock => sherlock_theme::palette().unwrap_or_else(Self::retro),
+            Self::Harmony { base, rule } => harmony::generate(*base, *rule),
+            Self::Random => harmony::random(&mut rand::rng()),
+            Self::Custom(colors) => colors.iter().map(|(c, _)| *c).collect(),
         }
     }
     fn party() -> ColorVec {
@@ -144,10 +227,26 @@ impl ColorPalette {
         ]
     }
 }
+impl ColorPalette {
+    /// Parses the `<color>[:<rule>]` part of a harmony palette. The rule
+    /// defaults to analogous.
+    fn parse_harmony(spec: &str) -> Result<Self, String> {
+        let (base, rule) = match spec.rsplit_once(':') {
+            Some((base, rule)) => (base, rule.parse::<HarmonyRule>()?),
+            None => (spec, HarmonyRule::Analogous),
+        };
+        Ok(Self::Harmony {
+            base: color::parse_color(base)?,
+            rule,
+        })
+    }
+}
+
 impl std::str::FromStr for ColorPalette {
     type Err = String; // or a custom error type
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
+        let name = s.to_lowercase();
+        match name.as_str() {
             "party" => Ok(ColorPalette::Party),
             "pastel" => Ok(ColorPalette::Pastel),
             "earth" => Ok(ColorPalette::Earth),
@@ -158,12 +257,22 @@ impl std::str::FromStr for ColorPalette {
             "retro" => Ok(ColorPalette::Retro),
             "forest" => Ok(ColorPalette::Forest),
             "candy" => Ok(ColorPalette::Candy),
-            _ => Err(format!("Unknown color pallette: {}", s)),
+            "sherlock" => Ok(ColorPalette::Sherlock),
+            "random" => Ok(ColorPalette::Random),
+            _ if name.starts_with("harmony:") => Self::parse_harmony(&s["harmony:".len()..]),
+            _ => match USER_PALETTES.get().and_then(|palettes| palettes.get(&name)) {
+                Some(colors) => Ok(ColorPalette::Custom(colors.clone())),
+                None => Err(format!(
+                    "Unknown color palette: {} (available: {})",
+                    s,
+                    Self::NAMES
+                        .iter()
+                        .copied()
+                        .chain(user_palette_names())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+            },
         }
     }
 }
-impl Default for ColorPalette {
-    fn default() -> Self {
-        Self::Retro
-    }
-}
@@ -0,0 +1,884 @@
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::Deserialize;
+use smithay_client_toolkit::shell::wlr_layer;
+
+use crate::{
+    cli, color,
+    color_palette::{ColorPalette, WeightedColors},
+    edge::EdgeMode,
+    emitter::Preset,
+    glyph,
+    origin::Origin,
+    palette_image,
+    pile::PileClear,
+    range::RandomRange,
+    shape::{self, Shape},
+    sprite::Sprite,
+};
+
+/// Settings for a single confetti run, resolved once in `main`.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub palette: ColorPalette,
+    /// Drop palette file colors that are too close to the theme background.
+    pub filter_background: bool,
+    /// Number of colors extracted from a palette image.
+    pub image_colors: u32,
+    /// Chroma multiplier for colors extracted from a palette image.
+    pub saturation_boost: f32,
+    /// Amount of per-particle lightness and chroma variation, 0 to 1.
+    pub color_variation: f32,
+    /// Seconds until the overlay closes. Without a duration the overlay closes
+    /// once all particles are gone, which continuous presets never are.
+    pub duration: Option<f32>,
+    /// Number of particles in the burst.
+    pub count: u32,
+    /// Particle size multiplier, `scalar` in canvas-confetti.
+    pub size: f32,
+    /// Particle shapes with their weights. Particles are squares if no
+    /// shapes, glyphs or sprites are given.
+    pub shapes: Vec<(Shape, u32)>,
+    /// Characters drawn as particles with their weights.
+    pub glyphs: Vec<(char, u32)>,
+    /// Font for the glyphs, fontconfig picks one per glyph if unset.
+    pub glyph_font: Option<PathBuf>,
+    /// Images drawn as particles.
+    pub sprites: Vec<Sprite>,
+    /// Multiply the sprites with the palette colors.
+    pub tint_sprites: bool,
+    /// Size multiplier per particle, on top of `size`.
+    pub size_scale: RandomRange,
+    /// Initial rotation in the particle's plane, in degrees.
+    pub rotation: RandomRange,
+    /// Rotation speed in the particle's plane, in degrees per second.
+    pub angular_velocity: RandomRange,
+    /// Seconds a burst particle waits before it is launched.
+    pub spawn_delay: RandomRange,
+    /// Lifetime of burst particles in multiples of `ticks`.
+    pub lifetime: RandomRange,
+    /// Opacity per particle, 0 to 1.
+    pub opacity: RandomRange,
+    /// Launch direction in degrees, 90 is straight up.
+    pub angle: f32,
+    /// Width of the launch cone in degrees.
+    pub spread: f32,
+    /// Initial speed in pixels per tick, randomized by ±50% per particle.
+    pub start_velocity: f32,
+    /// Fraction of the speed kept after every tick.
+    pub decay: f32,
+    /// Fall speed in multiples of 3 pixels per tick.
+    pub gravity: f32,
+    /// Sideways movement in pixels per tick.
+    pub drift: f32,
+    /// Steady wind in pixels per tick, y pointing down.
+    pub wind: [f32; 2],
+    /// Peak speed of gusts on top of the wind, in pixels per tick.
+    pub gusts: f32,
+    /// Speed of the swirling air in pixels per tick.
+    pub turbulence: f32,
+    /// Size of the swirls in pixels.
+    pub turbulence_scale: f32,
+    /// What particles do at the left and right edges of the screen.
+    pub edges: EdgeMode,
+    /// What particles do at the bottom edge of the screen.
+    pub floor: EdgeMode,
+    /// Fraction of the speed kept when bouncing off an edge.
+    pub restitution: f32,
+    /// Let falling particles land and pile up on the bottom edge.
+    pub pile: bool,
+    /// Seconds after the first particle landed until the pile is cleared.
+    pub pile_timeout: f32,
+    /// How the pile is cleared.
+    pub pile_clear: PileClear,
+    /// Lifetime in ticks, particles fade out over this time.
+    pub ticks: u32,
+    /// Launch point, resolved once the surface size is known.
+    pub origin: Origin,
+    /// Arrangement of emitters the particles are launched from.
+    pub preset: Preset,
+    /// Number of times the emitters fire.
+    pub waves: u32,
+    /// Seconds between two waves.
+    pub wave_interval: f32,
+    /// Particles spawned per second by continuous presets.
+    pub spawn_rate: f32,
+    /// Streamers launched with every confetti burst.
+    pub ribbons: u32,
+    /// Fixed RNG seed for reproducible bursts.
+    pub seed: Option<u64>,
+    /// Layer shell layer the overlay is placed on.
+    pub layer: Layer,
+    /// Layer shell namespace, useful for compositor rules.
+    pub namespace: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            palette: ColorPalette::default(),
+            filter_background: false,
+            image_colors: 6,
+            saturation_boost: 1.0,
+            color_variation: 0.0,
+            duration: None,
+            count: 200,
+            size: 1.0,
+            shapes: Vec::new(),
+            glyphs: Vec::new(),
+            glyph_font: None,
+            sprites: Vec::new(),
+            tint_sprites: false,
+            size_scale: RandomRange::fixed(1.0),
+            rotation: RandomRange::new(0.0, 360.0),
+            angular_velocity: RandomRange::fixed(0.0),
+            spawn_delay: RandomRange::fixed(0.0),
+            lifetime: RandomRange::fixed(1.0),
+            opacity: RandomRange::fixed(1.0),
+            angle: 90.0,
+            spread: 45.0,
+            start_velocity: 45.0,
+            decay: 0.9,
+            gravity: 1.0,
+            drift: 0.0,
+            wind: [0.0, 0.0],
+            gusts: 0.0,
+            turbulence: 0.0,
+            turbulence_scale: 150.0,
+            edges: EdgeMode::default(),
+            floor: EdgeMode::default(),
+            restitution: 0.6,
+            pile: false,
+            pile_timeout: 5.0,
+            pile_clear: PileClear::default(),
+            ticks: 200,
+            origin: Origin::default(),
+            preset: Preset::default(),
+            waves: 1,
+            wave_interval: 0.4,
+            spawn_rate: 40.0,
+            ribbons: 0,
+            seed: None,
+            layer: Layer::default(),
+            namespace: String::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Layers the file's default profile, the selected profile and the
+    /// command line overrides on top of the built-in defaults.
+    pub fn resolve(
+        file: &ConfigFile,
+        profile: Option<&str>,
+        overrides: &Profile,
+    ) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut source = None;
+        config
+            .apply(&file.default, &mut source, str::to_string)
+            .map_err(|e| format!("{}: [default]: {}", file.display_path(), e))?;
+
+        if let Some(name) = profile {
+            let selected = file
+                .profiles
+                .get(name)
+                .ok_or_else(|| format!("Unknown profile '{}' in {}", name, file.display_path()))?;
+            config
+                .apply(selected, &mut source, str::to_string)
+                .map_err(|e| format!("{}: [profiles.{}]: {}", file.display_path(), name, e))?;
+        }
+
+        config.apply(overrides, &mut source, cli::flag)?;
+
+        if let Some(source) = source {
+            config.palette = source.load(&config)?;
+        }
+        Ok(config)
+    }
+
+    /// Applies the settings of one layer. `key` names a setting in errors the
+    /// way the user wrote it, as config key or command line flag.
+    fn apply(
+        &mut self,
+        profile: &Profile,
+        source: &mut Option<PaletteSource>,
+        key: fn(&str) -> String,
+    ) -> Result<(), String> {
+        if let Some(selected) = PaletteSource::from_profile(profile, key)? {
+            *source = Some(selected);
+        }
+        if let Some(filter) = profile.filter_background {
+            self.filter_background = filter;
+        }
+        if let Some(image_colors) = profile.image_colors {
+            if !(1..=palette_image::MAX_COLORS).contains(&image_colors) {
+                return Err(format!(
+                    "Value for '{}' must be between 1 and {}",
+                    key("image_colors"),
+                    palette_image::MAX_COLORS
+                ));
+            }
+            self.image_colors = image_colors;
+        }
+        if let Some(boost) = profile.saturation_boost {
+            self.saturation_boost = positive(&key("saturation_boost"), boost)?;
+        }
+        if let Some(variation) = profile.color_variation {
+            if !(0.0..=1.0).contains(&variation) {
+                return Err(format!(
+                    "Value for '{}' must be between 0 and 1",
+                    key("color_variation")
+                ));
+            }
+            self.color_variation = variation;
+        }
+        if let Some(duration) = profile.duration {
+            self.duration = Some(positive(&key("duration"), duration)?);
+        }
+        if let Some(count) = profile.count {
+            if count == 0 {
+                return Err(format!("Value for '{}' must be at least 1", key("count")));
+            }
+            self.count = count;
+        }
+        if let Some(size) = profile.size {
+            self.size = positive(&key("size"), size)?;
+        }
+        if let Some(shapes) = &profile.shapes {
+            self.shapes = shape::parse_shape_list(shapes)?;
+        }
+        if let Some(glyphs) = &profile.glyphs {
+            self.glyphs = glyph::parse_glyph_list(glyphs)?;
+        }
+        if let Some(font) = &profile.glyph_font {
+            self.glyph_font = Some(font.clone());
+        }
+        if let Some(sprites) = &profile.sprites {
+            self.sprites = sprites.clone();
+        }
+        if let Some(tint) = profile.tint_sprites {
+            self.tint_sprites = tint;
+        }
+        if let Some(scale) = profile.size_scale {
+            if scale.min <= 0.0 {
+                return Err(format!(
+                    "Values for '{}' must be greater than 0",
+                    key("size_scale")
+                ));
+            }
+            self.size_scale = scale;
+        }
+        if let Some(rotation) = profile.rotation {
+            self.rotation = rotation;
+        }
+        if let Some(velocity) = profile.angular_velocity {
+            self.angular_velocity = velocity;
+        }
+        if let Some(delay) = profile.spawn_delay {
+            if delay.min < 0.0 {
+                return Err(format!(
+                    "Values for '{}' must not be negative",
+                    key("spawn_delay")
+                ));
+            }
+            self.spawn_delay = delay;
+        }
+        if let Some(lifetime) = profile.lifetime {
+            if lifetime.min <= 0.0 {
+                return Err(format!(
+                    "Values for '{}' must be greater than 0",
+                    key("lifetime")
+                ));
+            }
+            self.lifetime = lifetime;
+        }
+        if let Some(opacity) = profile.opacity {
+            if opacity.min < 0.0 || opacity.max > 1.0 {
+                return Err(format!(
+                    "Values for '{}' must be between 0 and 1",
+                    key("opacity")
+                ));
+            }
+            self.opacity = opacity;
+        }
+        if let Some(angle) = profile.angle {
+            self.angle = finite(&key("angle"), angle)?;
+        }
+        if let Some(spread) = profile.spread {
+            if !(0.0..=360.0).contains(&spread) {
+                return Err(format!(
+                    "Value for '{}' must be between 0 and 360",
+                    key("spread")
+                ));
+            }
+            self.spread = spread;
+        }
+        if let Some(velocity) = profile.start_velocity {
+            if finite(&key("start_velocity"), velocity)? < 0.0 {
+                return Err(format!(
+                    "Value for '{}' must not be negative",
+                    key("start_velocity")
+                ));
+            }
+            self.start_velocity = velocity;
+        }
+        if let Some(decay) = profile.decay {
+            if !(decay > 0.0 && decay <= 1.0) {
+                return Err(format!(
+                    "Value for '{}' must be greater than 0 and at most 1",
+                    key("decay")
+                ));
+            }
+            self.decay = decay;
+        }
+        if let Some(gravity) = profile.gravity {
+            self.gravity = finite(&key("gravity"), gravity)?;
+        }
+        if let Some(drift) = profile.drift {
+            self.drift = finite(&key("drift"), drift)?;
+        }
+        if let Some([x, y]) = profile.wind {
+            self.wind = [finite(&key("wind"), x)?, finite(&key("wind"), y)?];
+        }
+        if let Some(gusts) = profile.gusts {
+            if finite(&key("gusts"), gusts)? < 0.0 {
+                return Err(format!("Value for '{}' must not be negative", key("gusts")));
+            }
+            self.gusts = gusts;
+        }
+        if let Some(turbulence) = profile.turbulence {
+            if finite(&key("turbulence"), turbulence)? < 0.0 {
+                return Err(format!(
+                    "Value for '{}' must not be negative",
+                    key("turbulence")
+                ));
+            }
+            self.turbulence = turbulence;
+        }
+        if let Some(scale) = profile.turbulence_scale {
+            self.turbulence_scale = positive(&key("turbulence_scale"), scale)?;
+        }
+        if let Some(edges) = profile.edges {
+            self.edges = edges;
+        }
+        if let Some(floor) = profile.floor {
+            self.floor = floor;
+        }
+        if let Some(restitution) = profile.restitution {
+            if !(0.0..=1.0).contains(&restitution) {
+                return Err(format!(
+                    "Value for '{}' must be between 0 and 1",
+                    key("restitution")
+                ));
+            }
+            self.restitution = restitution;
+        }
+        if let Some(pile) = profile.pile {
+            self.pile = pile;
+        }
+        if let Some(timeout) = profile.pile_timeout {
+            if finite(&key("pile_timeout"), timeout)? < 0.0 {
+                return Err(format!(
+                    "Value for '{}' must not be negative",
+                    key("pile_timeout")
+                ));
+            }
+            self.pile_timeout = timeout;
+        }
+        if let Some(clear) = profile.pile_clear {
+            self.pile_clear = clear;
+        }
+        if let Some(ticks) = profile.ticks {
+            if ticks == 0 {
+                return Err(format!("Value for '{}' must be at least 1", key("ticks")));
+            }
+            self.ticks = ticks;
+        }
+        if let Some(origin) = profile.origin {
+            self.origin = origin;
+        }
+        if let Some(preset) = profile.preset {
+            self.preset = preset;
+        }
+        if let Some(waves) = profile.waves {
+            if waves == 0 {
+                return Err(format!("Value for '{}' must be at least 1", key("waves")));
+            }
+            self.waves = waves;
+        }
+        if let Some(interval) = profile.wave_interval {
+            if finite(&key("wave_interval"), interval)? < 0.0 {
+                return Err(format!(
+                    "Value for '{}' must not be negative",
+                    key("wave_interval")
+                ));
+            }
+            self.wave_interval = interval;
+        }
+        if let Some(rate) = profile.spawn_rate {
+            self.spawn_rate = positive(&key("spawn_rate"), rate)?;
+        }
+        if let Some(ribbons) = profile.ribbons {
+            self.ribbons = ribbons;
+        }
+        if let Some(seed) = profile.seed {
+            self.seed = Some(seed);
+        }
+        if let Some(layer) = profile.layer {
+            self.layer = layer;
+        }
+        if let Some(namespace) = &profile.namespace {
+            self.namespace = namespace.clone();
+        }
+        Ok(())
+    }
+}
+
+/// Where the palette comes from. Loading is deferred until all layers are
+/// applied, since it can depend on options set by a different layer.
+enum PaletteSource {
+    Named(String),
+    Colors(String),
+    File(PathBuf),
+    Image(PathBuf),
+}
+
+impl PaletteSource {
+    fn from_profile(profile: &Profile, key: fn(&str) -> String) -> Result<Option<Self>, String> {
+        let mut sources = [
+            profile.palette.clone().map(Self::Named),
+            profile.colors.clone().map(Self::Colors),
+            profile.palette_file.clone().map(Self::File),
+            profile.palette_image.clone().map(Self::Image),
+        ]
+        .into_iter()
+        .flatten();
+        let source = sources.next();
+        if sources.next().is_some() {
+            return Err(format!(
+                "Only one of '{}', '{}', '{}' and '{}' can be used",
+                key("palette"),
+                key("colors"),
+                key("palette_file"),
+                key("palette_image")
+            ));
+        }
+        Ok(source)
+    }
+
+    fn load(&self, config: &Config) -> Result<ColorPalette, String> {
+        match self {
+            Self::Named(name) => name.parse::<ColorPalette>(),
+            Self::Colors(colors) => color::parse_color_list(colors).map(ColorPalette::Custom),
+            Self::File(path) => {
+                ColorPalette::from_file(&expand_home(path), config.filter_background)
+            }
+            Self::Image(path) => ColorPalette::from_image(
+                &expand_home(path),
+                config.image_colors as usize,
+                config.saturation_boost,
+            ),
+        }
+    }
+}
+
+/// Returns `$XDG_CONFIG_HOME`, falling back to `~/.config`.
+pub fn config_home() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+}
+
+/// Expands a leading `~` to the home directory.
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn finite(name: &str, value: f32) -> Result<f32, String> {
+    if !value.is_finite() {
+        return Err(format!("Value for '{}' must be a finite number", name));
+    }
+    Ok(value)
+}
+
+fn positive(name: &str, value: f32) -> Result<f32, String> {
+    if finite(name, value)? <= 0.0 {
+        return Err(format!("Value for '{}' must be greater than 0", name));
+    }
+    Ok(value)
+}
+
+/// A partial set of settings. Used for the profiles in the config file as
+/// well as for the command line overrides.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub palette: Option<String>,
+    /// Ad-hoc palette as a comma separated list of colors.
+    pub colors: Option<String>,
+    /// Theme file to import the palette from.
+    pub palette_file: Option<PathBuf>,
+    pub filter_background: Option<bool>,
+    /// Image, e.g. the wallpaper, to extract the palette from.
+    pub palette_image: Option<PathBuf>,
+    pub image_colors: Option<u32>,
+    pub saturation_boost: Option<f32>,
+    pub color_variation: Option<f32>,
+    pub duration: Option<f32>,
+    pub count: Option<u32>,
+    #[serde(alias = "scalar")]
+    pub size: Option<f32>,
+    /// Comma separated shapes with optional weights, e.g. `"square:3,star"`.
+    pub shapes: Option<String>,
+    /// Comma separated characters with optional weights, e.g. `"🎉:2,$"`.
+    pub glyphs: Option<String>,
+    pub glyph_font: Option<PathBuf>,
+    /// Image files with optional weights, e.g. `["logo.svg:3", "~/star.png"]`.
+    pub sprites: Option<Vec<Sprite>>,
+    pub tint_sprites: Option<bool>,
+    pub size_scale: Option<RandomRange>,
+    pub rotation: Option<RandomRange>,
+    pub angular_velocity: Option<RandomRange>,
+    pub spawn_delay: Option<RandomRange>,
+    pub lifetime: Option<RandomRange>,
+    pub opacity: Option<RandomRange>,
+    pub angle: Option<f32>,
+    pub spread: Option<f32>,
+    pub start_velocity: Option<f32>,
+    pub decay: Option<f32>,
+    pub gravity: Option<f32>,
+    pub drift: Option<f32>,
+    pub wind: Option<[f32; 2]>,
+    pub gusts: Option<f32>,
+    pub turbulence: Option<f32>,
+    pub turbulence_scale: Option<f32>,
+    pub edges: Option<EdgeMode>,
+    pub floor: Option<EdgeMode>,
+    pub restitution: Option<f32>,
+    pub pile: Option<bool>,
+    pub pile_timeout: Option<f32>,
+    pub pile_clear: Option<PileClear>,
+    pub ticks: Option<u32>,
+    pub origin: Option<Origin>,
+    pub preset: Option<Preset>,
+    pub waves: Option<u32>,
+    pub wave_interval: Option<f32>,
+    pub spawn_rate: Option<f32>,
+    pub ribbons: Option<u32>,
+    pub seed: Option<u64>,
+    pub layer: Option<Layer>,
+    pub namespace: Option<String>,
+}
+
+/// Contents of `$XDG_CONFIG_HOME/confetti/config.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(default)]
+    pub default: Profile,
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+    #[serde(default, rename = "palettes")]
+    palette_entries: HashMap<String, Vec<PaletteEntry>>,
+    /// Custom palettes, resolved from `palette_entries` on load.
+    #[serde(skip)]
+    pub palettes: HashMap<String, WeightedColors>,
+}
+
+/// A color in a `[palettes]` list: either an RGB triple in `0.0..=1.0` or a
+/// color string such as `"#ff0066:3"` or `"hsl(45 100% 50%)"`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PaletteEntry {
+    Rgb([f32; 3]),
+    Css(String),
+}
+
+impl ConfigFile {
+    /// Loads the config file. A missing file yields an empty config.
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        Self::parse(&content, path)
+    }
+
+    /// Parses the contents of the config file at `path`, which is only used
+    /// in error messages.
+    fn parse(content: &str, path: PathBuf) -> Result<Self, String> {
+        let mut file = toml::from_str::<ConfigFile>(content).map_err(|e| {
+            let (line, column) = e
+                .span()
+                .map(|span| line_column(content, span.start))
+                .unwrap_or((1, 1));
+            format!(
+                "{}:{}:{}: {}",
+                path.display(),
+                line,
+                column,
+                e.message().trim_end()
+            )
+        })?;
+        file.path = Some(path);
+
+        for (name, entries) in &file.palette_entries {
+            if ColorPalette::NAMES.contains(&name.to_lowercase().as_str()) {
+                return Err(format!(
+                    "{}: palette '{}' shadows a built-in palette",
+                    file.display_path(),
+                    name
+                ));
+            }
+            let colors = Self::palette_colors(entries)
+                .map_err(|e| format!("{}: palette '{}': {}", file.display_path(), name, e))?;
+            file.palettes.insert(name.clone(), colors);
+        }
+        Ok(file)
+    }
+
+    fn palette_colors(entries: &[PaletteEntry]) -> Result<WeightedColors, String> {
+        let mut colors = WeightedColors::new();
+        for entry in entries {
+            match entry {
+                PaletteEntry::Rgb(rgb) => {
+                    if rgb.iter().any(|c| !(0.0..=1.0).contains(c)) {
+                        return Err(format!(
+                            "color {:?} has channels outside of 0.0 to 1.0",
+                            rgb
+                        ));
+                    }
+                    colors.push((*rgb, 1));
+                }
+                PaletteEntry::Css(token) => colors.push(color::parse_weighted_color(token)?),
+            }
+        }
+        if colors.is_empty() {
+            return Err("palette has no colors".to_string());
+        }
+        Ok(colors)
+    }
+
+    fn path() -> Option<PathBuf> {
+        Some(config_home()?.join("confetti").join("config.toml"))
+    }
+
+    fn display_path(&self) -> String {
+        self.path
+            .as_ref()
+            .map_or_else(|| "config".to_string(), |p| p.display().to_string())
+    }
+}
+
+/// Converts a byte offset into a 1-based line and column.
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, column)
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layer {
+    Background,
+    Bottom,
+    #[default]
+    Top,
+    Overlay,
+}
+
+impl FromStr for Layer {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "background" => Ok(Self::Background),
+            "bottom" => Ok(Self::Bottom),
+            "top" => Ok(Self::Top),
+            "overlay" => Ok(Self::Overlay),
+            _ => Err(format!(
+                "Unknown layer: {} (available: background, bottom, top, overlay)",
+                s
+            )),
+        }
+    }
+}
+
+impl From<Layer> for wlr_layer::Layer {
+    fn from(layer: Layer) -> Self {
+        match layer {
+            Layer::Background => Self::Background,
+            Layer::Bottom => Self::Bottom,
+            Layer::Top => Self::Top,
+            Layer::Overlay => Self::Overlay,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(content: &str) -> Result<ConfigFile, String> {
+        ConfigFile::parse(content, PathBuf::from("config.toml"))
+    }
+
+    fn resolve(
+        content: &str,
+        profile: Option<&str>,
+        overrides: &Profile,
+    ) -> Result<Config, String> {
+        Config::resolve(&file(content)?, profile, overrides)
+    }
+
+    const LAYERS: &str = r#"
+        [default]
+        size = 2.0
+        count = 10
+
+        [profiles.big]
+        size = 3.0
+    "#;
+
+    #[test]
+    fn layers_default_profile_and_command_line() {
+        let config = resolve(LAYERS, None, &Profile::default()).unwrap();
+        assert_eq!((config.size, config.count), (2.0, 10));
+
+        let config = resolve(LAYERS, Some("big"), &Profile::default()).unwrap();
+        assert_eq!((config.size, config.count), (3.0, 10));
+
+        let overrides = Profile {
+            size: Some(4.0),
+            ..Profile::default()
+        };
+        let config = resolve(LAYERS, Some("big"), &overrides).unwrap();
+        assert_eq!((config.size, config.count), (4.0, 10));
+
+        let config = resolve("", None, &Profile::default()).unwrap();
+        assert_eq!(config.size, Config::default().size);
+    }
+
+    #[test]
+    fn later_layers_replace_the_palette_source() {
+        let content = r##"
+            [default]
+            palette = "neon"
+
+            [profiles.custom]
+            colors = "#ff0066, #00ccff"
+        "##;
+        let config = resolve(content, Some("custom"), &Profile::default()).unwrap();
+        assert!(matches!(config.palette, ColorPalette::Custom(_)));
+
+        let overrides = Profile {
+            palette: Some("ocean".to_string()),
+            ..Profile::default()
+        };
+        let config = resolve(content, Some("custom"), &overrides).unwrap();
+        assert!(matches!(config.palette, ColorPalette::Ocean));
+    }
+
+    #[test]
+    fn rejects_two_palette_sources_in_one_layer() {
+        let content = r##"
+            [default]
+            palette = "neon"
+            colors = "#ff0066"
+        "##;
+        assert_eq!(
+            resolve(content, None, &Profile::default()).unwrap_err(),
+            "config.toml: [default]: Only one of 'palette', 'colors', 'palette_file' and \
+             'palette_image' can be used"
+        );
+
+        let overrides = Profile {
+            palette: Some("neon".to_string()),
+            palette_image: Some(PathBuf::from("wallpaper.png")),
+            ..Profile::default()
+        };
+        assert_eq!(
+            resolve("", None, &overrides).unwrap_err(),
+            "Only one of '--palette', '--colors', '--palette-file' and \
+             '--palette-from-image' can be used"
+        );
+    }
+
+    #[test]
+    fn names_the_layer_of_invalid_values() {
+        let content = r##"
+            [profiles.empty]
+            count = 0
+        "##;
+        assert_eq!(
+            resolve(content, Some("empty"), &Profile::default()).unwrap_err(),
+            "config.toml: [profiles.empty]: Value for 'count' must be at least 1"
+        );
+        assert_eq!(
+            resolve(LAYERS, Some("huge"), &Profile::default()).unwrap_err(),
+            "Unknown profile 'huge' in config.toml"
+        );
+
+        let overrides = Profile {
+            count: Some(0),
+            ..Profile::default()
+        };
+        assert_eq!(
+            resolve("", None, &overrides).unwrap_err(),
+            "Value for '--count' must be at least 1"
+        );
+    }
+
+    #[test]
+    fn reads_custom_palettes() {
+        let file = file(
+            r##"
+            [palettes]
+            mine = ["#ff0066:3", [0.0, 0.8, 1.0]]
+            "##,
+        )
+        .unwrap();
+        let weights = file.palettes["mine"]
+            .iter()
+            .map(|(_, w)| *w)
+            .collect::<Vec<_>>();
+        assert_eq!(weights, [3, 1]);
+    }
+
+    #[test]
+    fn rejects_invalid_custom_palettes() {
+        let error = |content: &str| file(content).unwrap_err();
+        assert_eq!(
+            error("[palettes]\nNeon = [\"#ff0066\"]"),
+            "config.toml: palette 'Neon' shadows a built-in palette"
+        );
+        assert_eq!(
+            error("[palettes]\nmine = []"),
+            "config.toml: palette 'mine': palette has no colors"
+        );
+        assert_eq!(
+            error("[palettes]\nmine = [[1.0, 2.0, 0.0]]"),
+            "config.toml: palette 'mine': color [1.0, 2.0, 0.0] has channels outside of 0.0 to 1.0"
+        );
+        assert_eq!(
+            error("[default]\nsize = \"big\""),
+            "config.toml:2:8: invalid type: string \"big\", expected f32"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// What happens to a particle that reaches the side edges or the floor of the
+/// screen. The top edge is always open, particles fall back down anyway.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeMode {
+    /// Stops at the edge and slides along it.
+    Clamp,
+    /// Bounces off, keeping `restitution` of its speed.
+    Bounce,
+    /// Leaves the screen and comes back in on the opposite side.
+    Wrap,
+    /// Is removed once it is out of view.
+    #[default]
+    Despawn,
+}
+
+impl EdgeMode {
+    pub const NAMES: [&'static str; 4] = ["clamp", "bounce", "wrap", "despawn"];
+
+    /// Applies the mode to the left and right edges at `0` and `width`.
+    /// `velocity` is the particle's own speed and `carried` the constant
+    /// movement on top of it, like the drift. Wrapping and despawning happen
+    /// `margin` pixels outside the edges. Returns false if the particle is
+    /// to be removed.
+    pub fn sides(
+        self,
+        x: &mut f32,
+        velocity: &mut f32,
+        carried: f32,
+        width: f32,
+        margin: f32,
+        restitution: f32,
+    ) -> bool {
+        match self {
+            Self::Clamp => *x = x.clamp(0.0, width),
+            Self::Bounce if *x < 0.0 => reflect(x, velocity, carried, 0.0, restitution),
+            Self::Bounce if *x > width => reflect(x, velocity, carried, width, restitution),
+            Self::Bounce => {}
+            Self::Wrap if *x < -margin => *x += width + 2.0 * margin,
+            Self::Wrap if *x > width + margin => *x -= width + 2.0 * margin,
+            Self::Wrap => {}
+            Self::Despawn => return (-margin..=width + margin).contains(x),
+        }
+        true
+    }
+
+    /// Applies the mode to the floor at `height`, with `carried` being the
+    /// constant fall speed. Wrapped particles reappear above the top edge.
+    pub fn floor(
+        self,
+        y: &mut f32,
+        velocity: &mut f32,
+        carried: f32,
+        height: f32,
+        margin: f32,
+        restitution: f32,
+    ) -> bool {
+        match self {
+            Self::Clamp => *y = y.min(height),
+            Self::Bounce if *y > height => reflect(y, velocity, carried, height, restitution),
+            Self::Bounce => {}
+            Self::Wrap if *y > height + margin => *y -= height + 2.0 * margin,
+            Self::Wrap => {}
+            Self::Despawn => return *y <= height + margin,
+        }
+        true
+    }
+}
+
+/// Mirrors the position at the edge and reverses the total speed, so the
+/// particle moves away at `restitution` times the speed it hit the edge with.
+fn reflect(position: &mut f32, velocity: &mut f32, carried: f32, edge: f32, restitution: f32) {
+    *position = 2.0 * edge - *position;
+    *velocity = -(*velocity + carried) * restitution - carried;
+}
+
+impl FromStr for EdgeMode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "clamp" => Ok(Self::Clamp),
+            "bounce" => Ok(Self::Bounce),
+            "wrap" => Ok(Self::Wrap),
+            "despawn" => Ok(Self::Despawn),
+            _ => Err(format!(
+                "Unknown edge mode: {} (available: {})",
+                s,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounce_keeps_restitution_of_the_total_speed() {
+        let (mut y, mut velocity) = (105.0, 3.0);
+        assert!(EdgeMode::Bounce.floor(&mut y, &mut velocity, 2.0, 100.0, 20.0, 0.5));
+        assert_eq!(y, 95.0);
+        // Hit the floor at 3 + 2, leaves at half that upwards
+        assert_eq!(velocity + 2.0, -2.5);
+
+        let (mut x, mut velocity) = (-4.0, -6.0);
+        assert!(EdgeMode::Bounce.sides(&mut x, &mut velocity, 0.0, 100.0, 20.0, 1.0));
+        assert_eq!((x, velocity), (4.0, 6.0));
+
+        let (mut x, mut velocity) = (50.0, -6.0);
+        assert!(EdgeMode::Bounce.sides(&mut x, &mut velocity, 0.0, 100.0, 20.0, 1.0));
+        assert_eq!((x, velocity), (50.0, -6.0));
+    }
+
+    #[test]
+    fn clamp_stops_at_the_edge() {
+        let (mut x, mut velocity) = (104.0, 3.0);
+        assert!(EdgeMode::Clamp.sides(&mut x, &mut velocity, 0.0, 100.0, 20.0, 0.5));
+        assert_eq!((x, velocity), (100.0, 3.0));
+
+        let (mut y, mut velocity) = (130.0, 3.0);
+        assert!(EdgeMode::Clamp.floor(&mut y, &mut velocity, 2.0, 100.0, 20.0, 0.5));
+        assert_eq!(y, 100.0);
+    }
+
+    #[test]
+    fn wrap_comes_back_on_the_opposite_side() {
+        let (mut x, mut velocity) = (125.0, 3.0);
+        assert!(EdgeMode::Wrap.sides(&mut x, &mut velocity, 0.0, 100.0, 20.0, 0.5));
+        assert_eq!(x, -15.0);
+
+        let (mut x, mut velocity) = (-25.0, -3.0);
+        assert!(EdgeMode::Wrap.sides(&mut x, &mut velocity, 0.0, 100.0, 20.0, 0.5));
+        assert_eq!(x, 115.0);
+
+        // Inside the margin nothing happens yet
+        let (mut y, mut velocity) = (110.0, 3.0);
+        assert!(EdgeMode::Wrap.floor(&mut y, &mut velocity, 2.0, 100.0, 20.0, 0.5));
+        assert_eq!(y, 110.0);
+        let mut y = 125.0;
+        assert!(EdgeMode::Wrap.floor(&mut y, &mut velocity, 2.0, 100.0, 20.0, 0.5));
+        assert_eq!(y, -15.0);
+    }
+
+    #[test]
+    fn despawn_removes_particles_beyond_the_margin() {
+        let mut velocity = 0.0;
+        for (x, inside) in [(-25.0, false), (-15.0, true), (115.0, true), (125.0, false)] {
+            let mut x = x;
+            let kept = EdgeMode::Despawn.sides(&mut x, &mut velocity, 0.0, 100.0, 20.0, 0.5);
+            assert_eq!(kept, inside, "at {}", x);
+        }
+        let mut y = 125.0;
+        assert!(!EdgeMode::Despawn.floor(&mut y, &mut velocity, 2.0, 100.0, 20.0, 0.5));
+    }
+
+    #[test]
+    fn parses_mode_names() {
+        assert_eq!("Bounce".parse(), Ok(EdgeMode::Bounce));
+        assert_eq!(
+            "stick".parse::<EdgeMode>(),
+            Err("Unknown edge mode: stick (available: clamp, bounce, wrap, despawn)".to_string())
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::{config::Config, origin::Origin};
+
+/// What an emitter launches.
+#[derive(Debug, Clone, Copy)]
+pub enum Payload {
+    Confetti,
+    /// A single rocket that bursts into `count` sparks at its highest point.
+    Rocket,
+    /// Particles dropping from the top edge with a swaying motion, spawned
+    /// continuously at `rate` per second. At most `count` are on screen.
+    Stream {
+        rate: f32,
+        /// Fall speed in pixels per tick.
+        speed: f32,
+        /// Peak sideways speed of the sway in pixels per tick.
+        sway: f32,
+    },
+}
+
+/// A source of particles that fires one burst or, for streams, keeps
+/// spawning particles.
+#[derive(Debug, Clone, Copy)]
+pub struct Emitter {
+    pub payload: Payload,
+    pub origin: Origin,
+    /// Launch direction in degrees, 90 is straight up.
+    pub angle: f32,
+    /// Width of the launch cone in degrees.
+    pub spread: f32,
+    pub start_velocity: f32,
+    /// Number of particles in the burst.
+    pub count: u32,
+    /// Seconds after the start before the burst is fired.
+    pub delay: f32,
+}
+
+/// Arrangements of emitters.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    /// A single burst configured by `origin`, `angle` and `spread`.
+    #[default]
+    Burst,
+    /// Two cannons in the bottom corners firing towards the center.
+    Cannons,
+    /// Rockets rising from the bottom edge and exploding into sparks.
+    Fireworks,
+    /// Slowly falling and swaying particles, until stopped.
+    Snow,
+    /// Confetti raining down from the top edge, until stopped.
+    Rain,
+}
+
+impl Preset {
+    pub const NAMES: [&'static str; 5] = ["burst", "cannons", "fireworks", "snow", "rain"];
+
+    /// Whether the preset keeps spawning particles until it is stopped.
+    pub fn is_continuous(&self) -> bool {
+        matches!(self, Self::Snow | Self::Rain)
+    }
+
+    /// Returns the emitters of every wave. The particle count of a wave is
+    /// split evenly between its emitters. Continuous presets fire only once.
+    pub fn emitters(&self, config: &Config) -> Vec<Emitter> {
+        let wave = match self {
+            Self::Burst => vec![Emitter {
+                payload: Payload::Confetti,
+                origin: config.origin,
+                angle: config.angle,
+                spread: config.spread,
+                start_velocity: config.start_velocity,
+                count: config.count,
+                delay: 0.0,
+            }],
+            Self::Cannons => [
+                (Origin::fraction(0.0, 1.0), 60.0, config.count / 2),
+                (
+                    Origin::fraction(1.0, 1.0),
+                    120.0,
+                    config.count - config.count / 2,
+                ),
+            ]
+            .into_iter()
+            .map(|(origin, angle, count)| Emitter {
+                payload: Payload::Confetti,
+                origin,
+                angle,
+                spread: config.spread,
+                start_velocity: config.start_velocity,
+                count,
+                delay: 0.0,
+            })
+            .collect(),
+            // Staggered so the explosions don't all happen at once
+            Self::Fireworks => [(0.3, 0.0), (0.7, 0.3), (0.5, 0.6)]
+                .into_iter()
+                .enumerate()
+                .map(|(i, (x, delay))| Emitter {
+                    payload: Payload::Rocket,
+                    origin: Origin::fraction(x, 1.0),
+                    angle: 90.0,
+                    spread: 12.0,
+                    start_velocity: config.start_velocity,
+                    // The first rockets get the remainder
+                    count: config.count / 3 + u32::from((i as u32) < config.count % 3),
+                    delay,
+                })
+                .collect(),
+            Self::Snow | Self::Rain => {
+                let (speed, sway) = match self {
+                    Self::Snow => (1.5, 1.2),
+                    _ => (5.0, 0.5),
+                };
+                vec![Emitter {
+                    payload: Payload::Stream {
+                        rate: config.spawn_rate,
+                        speed: speed * config.gravity,
+                        sway,
+                    },
+                    origin: Origin::fraction(0.5, 0.0),
+                    angle: -90.0,
+                    spread: 0.0,
+                    start_velocity: 0.0,
+                    count: config.count,
+                    delay: 0.0,
+                }]
+            }
+        };
+        if self.is_continuous() {
+            return wave;
+        }
+
+        (0..config.waves)
+            .flat_map(|i| {
+                wave.iter().map(move |emitter| Emitter {
+                    delay: emitter.delay + i as f32 * config.wave_interval,
+                    ..*emitter
+                })
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "burst" => Ok(Self::Burst),
+            "cannons" => Ok(Self::Cannons),
+            "fireworks" => Ok(Self::Fireworks),
+            "snow" => Ok(Self::Snow),
+            "rain" => Ok(Self::Rain),
+            _ => Err(format!(
+                "Unknown preset: {} (available: {})",
+                s,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(preset: Preset, count: u32) -> Vec<u32> {
+        let config = Config {
+            count,
+            ..Config::default()
+        };
+        preset.emitters(&config).iter().map(|e| e.count).collect()
+    }
+
+    #[test]
+    fn cannons_split_the_count_exactly() {
+        assert_eq!(counts(Preset::Cannons, 100), [50, 50]);
+        assert_eq!(counts(Preset::Cannons, 7), [3, 4]);
+        assert_eq!(counts(Preset::Cannons, 1), [0, 1]);
+    }
+
+    #[test]
+    fn rockets_split_the_count_exactly() {
+        assert_eq!(counts(Preset::Fireworks, 99), [33, 33, 33]);
+        assert_eq!(counts(Preset::Fireworks, 100), [34, 33, 33]);
+        assert_eq!(counts(Preset::Fireworks, 101), [34, 34, 33]);
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use ab_glyph::{Font, FontVec, GlyphImageFormat, PxScale};
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    atlas::{self, Cell},
+    config, weighted,
+};
+
+/// Parses a comma separated list of single characters, each with an optional
+/// `:weight` suffix.
+pub fn parse_glyph_list(list: &str) -> Result<Vec<(char, u32)>, String> {
+    let mut glyphs = Vec::new();
+    for token in list.split(',') {
+        if token.trim().is_empty() {
+            continue;
+        }
+        let (glyph, weight) = weighted::split_weight(token)?;
+        // Variation selectors only pick between text and emoji presentation
+        let mut chars = glyph
+            .chars()
+            .filter(|c| !matches!(c, '\u{fe0e}' | '\u{fe0f}'));
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            return Err(format!("Glyph '{}' must be a single character", glyph));
+        };
+        glyphs.push((c, weight));
+    }
+    if glyphs.is_empty() {
+        return Err(format!("No glyphs in '{}'", list));
+    }
+    Ok(glyphs)
+}
+
+/// Fonts used to rasterize glyphs: either a single given font or whatever
+/// fontconfig picks for each character.
+pub struct Fonts {
+    given: Option<(PathBuf, FontVec)>,
+    /// System fonts by file and face index.
+    system: HashMap<(PathBuf, u32), FontVec>,
+}
+
+impl Fonts {
+    pub fn new(path: Option<&Path>) -> Result<Self, String> {
+        let given = match path {
+            Some(path) => {
+                let path = config::expand_home(path);
+                let font = load_font(&path, 0)?;
+                Some((path, font))
+            }
+            None => None,
+        };
+        Ok(Self {
+            given,
+            system: HashMap::new(),
+        })
+    }
+
+    /// Rasterizes a glyph into an atlas cell. Color emoji keep their colors,
+    /// outline glyphs are drawn white to be tinted with the palette.
+    pub fn rasterize(&mut self, c: char) -> Result<Cell, String> {
+        let font = self.font_for(c)?;
+        let id = font.glyph_id(c);
+
+        // Color emoji fonts store bitmaps, the largest one scales down best
+        if let Some(raster) = font.glyph_raster_image2(id, u16::MAX) {
+            let image = match raster.format {
+                GlyphImageFormat::Png => image::load_from_memory(raster.data)
+                    .map(|image| image.to_rgba8())
+                    .ok(),
+                GlyphImageFormat::BitmapPremulBgra32 => {
+                    bgra_image(raster.width, raster.height, raster.data)
+                }
+                _ => None,
+            };
+            if let Some(image) = image {
+                return Ok(Cell {
+                    image: atlas::fit_to_cell(&image),
+                    tinted: false,
+                });
+            }
+        }
+
+        let inner = (atlas::CELL_SIZE - 2 * atlas::CELL_PADDING) as f32;
+        let outline = |scale: f32| font.outline_glyph(id.with_scale(PxScale::from(scale)));
+        let probe = outline(inner)
+            .ok_or_else(|| format!("Glyph '{}' has nothing to draw", c))?
+            .px_bounds();
+        // Scaled so the larger side of the glyph fills the cell
+        let fit = inner / probe.width().max(probe.height()).max(1.0);
+        let glyph =
+            outline(inner * fit).ok_or_else(|| format!("Glyph '{}' has nothing to draw", c))?;
+        let bounds = glyph.px_bounds();
+        let mut image = RgbaImage::new(
+            (bounds.width().ceil() as u32).max(1),
+            (bounds.height().ceil() as u32).max(1),
+        );
+        glyph.draw(|x, y, coverage| {
+            if x < image.width() && y < image.height() {
+                let alpha = (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+                image.put_pixel(x, y, Rgba([255, 255, 255, alpha]));
+            }
+        });
+        Ok(Cell {
+            image: atlas::fit_to_cell(&image),
+            tinted: true,
+        })
+    }
+
+    fn font_for(&mut self, c: char) -> Result<&FontVec, String> {
+        if let Some((path, font)) = &self.given {
+            if font.glyph_id(c).0 == 0 {
+                return Err(format!("Glyph '{}' is missing from {}", c, path.display()));
+            }
+            return Ok(font);
+        }
+
+        let (path, index) = match_font(c)?;
+        let key = (path.clone(), index);
+        if !self.system.contains_key(&key) {
+            self.system.insert(key.clone(), load_font(&path, index)?);
+        }
+        let font = &self.system[&key];
+        if font.glyph_id(c).0 == 0 {
+            return Err(format!(
+                "No installed font has the glyph '{}', pass one with --glyph-font",
+                c
+            ));
+        }
+        Ok(font)
+    }
+}
+
+/// Asks fontconfig for the best installed font covering `c`.
+fn match_font(c: char) -> Result<(PathBuf, u32), String> {
+    let output = Command::new("fc-match")
+        .arg("--format=%{file}\n%{index}")
+        .arg(format!(":charset={:x}", c as u32))
+        .output()
+        .map_err(|e| {
+            format!(
+                "Failed to run fc-match: {}, pass a font with --glyph-font",
+                e
+            )
+        })?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let (file, index) = output
+        .rsplit_once('\n')
+        .filter(|(file, _)| !file.is_empty())
+        .ok_or_else(|| format!("fc-match found no font for the glyph '{}'", c))?;
+    Ok((PathBuf::from(file), index.trim().parse().unwrap_or(0)))
+}
+
+fn load_font(path: &Path, index: u32) -> Result<FontVec, String> {
+    let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    FontVec::try_from_vec_and_index(data, index).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Converts a premultiplied BGRA bitmap to straight RGBA.
+fn bgra_image(width: u16, height: u16, data: &[u8]) -> Option<RgbaImage> {
+    let pixels = data
+        .chunks_exact(4)
+        .flat_map(|p| {
+            let unpremultiply = |c: u8| match p[3] {
+                0 => 0,
+                a => (c as u32 * 255 / a as u32).min(255) as u8,
+            };
+            [
+                unpremultiply(p[2]),
+                unpremultiply(p[1]),
+                unpremultiply(p[0]),
+                p[3],
+            ]
+        })
+        .collect();
+    RgbaImage::from_raw(width as u32, height as u32, pixels)
+}
@@ -0,0 +1,91 @@
+use std::str::FromStr;
+
+use rand::Rng;
+
+use crate::{color, color_palette::ColorVec};
+
+/// Color harmony rules for generating a palette from a base color.
+#[derive(Debug, Clone, Copy)]
+pub enum HarmonyRule {
+    Complementary,
+    Analogous,
+    Triadic,
+    Tetradic,
+    Monochrome,
+}
+
+impl HarmonyRule {
+    pub const NAMES: [&'static str; 5] = [
+        "complementary",
+        "analogous",
+        "triadic",
+        "tetradic",
+        "monochrome",
+    ];
+
+    /// Hue offsets in degrees from the base color.
+    fn hue_offsets(&self) -> &'static [f32] {
+        match self {
+            Self::Complementary => &[0.0, 180.0],
+            Self::Analogous => &[-60.0, -30.0, 0.0, 30.0, 60.0],
+            Self::Triadic => &[0.0, 120.0, 240.0],
+            Self::Tetradic => &[0.0, 90.0, 180.0, 270.0],
+            // Varies the lightness instead, see `from_lch`
+            Self::Monochrome => unreachable!("monochrome palettes keep the hue"),
+        }
+    }
+}
+
+impl FromStr for HarmonyRule {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "complementary" => Ok(Self::Complementary),
+            "analogous" => Ok(Self::Analogous),
+            "triadic" => Ok(Self::Triadic),
+            "tetradic" => Ok(Self::Tetradic),
+            "monochrome" => Ok(Self::Monochrome),
+            _ => Err(format!(
+                "Unknown harmony rule: {} (available: {})",
+                s,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Generates a palette around an sRGB base color. The colors share the base's
+/// OKLCH lightness and chroma, except for monochrome palettes which vary in
+/// lightness instead of hue.
+pub fn generate(base: [f32; 3], rule: HarmonyRule) -> ColorVec {
+    let [lightness, chroma, hue] = color::oklab_to_oklch(color::srgb_to_oklab(base));
+    from_lch(lightness, chroma, hue, rule)
+}
+
+/// Generates a fresh palette with a random hue and harmony rule, using
+/// lightness and chroma ranges that look good as confetti.
+pub fn random(rng: &mut impl Rng) -> ColorVec {
+    let rule = match rng.random_range(0..4) {
+        0 => HarmonyRule::Complementary,
+        1 => HarmonyRule::Analogous,
+        2 => HarmonyRule::Triadic,
+        _ => HarmonyRule::Tetradic,
+    };
+    let lightness = rng.random_range(0.68..0.84);
+    let chroma = rng.random_range(0.12..0.2);
+    let hue = rng.random_range(0.0..360.0);
+    from_lch(lightness, chroma, hue, rule)
+}
+
+fn from_lch(lightness: f32, chroma: f32, hue: f32, rule: HarmonyRule) -> ColorVec {
+    if let HarmonyRule::Monochrome = rule {
+        return [0.45, 0.6, 0.75, 0.9]
+            .iter()
+            .map(|l| color::oklch_to_srgb([*l, chroma, hue]))
+            .collect();
+    }
+    rule.hue_offsets()
+        .iter()
+        .map(|offset| color::oklch_to_srgb([lightness, chroma, hue + offset]))
+        .collect()
+}
@@ -39,21 +39,15 @@ impl LayerShellHandler for Wgpu {
         self.width = NonZeroU32::new(configure.new_size.0).map_or(256, NonZeroU32::get);
         self.height = NonZeroU32::new(configure.new_size.1).map_or(256, NonZeroU32::get);
 
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: wgpu::TextureFormat::Bgra8Unorm,
-            width: self.width,
-            height: self.height,
-            present_mode: wgpu::PresentMode::Fifo,
-            alpha_mode: wgpu::CompositeAlphaMode::PreMultiplied,
-            view_formats: vec![],
-            desired_maximum_frame_latency: 0,
-        };
+        self.surface_config.width = self.width;
+        self.surface_config.height = self.height;
+        self.uniforms.resolution = [self.width as f32, self.height as f32];
+        self.simulation.resize(self.width, self.height);
 
         // Initiate the first draw.
         if self.first_configure {
             self.first_configure = false;
-            self.surface.configure(&self.device, &config);
+            self.surface.configure(&self.device, &self.surface_config);
         }
         loop {
             if self.exit {
@@ -1,4 +1,3 @@
-use rand::Rng;
 use raw_window_handle::{
     RawDisplayHandle, RawWindowHandle, WaylandDisplayHandle, WaylandWindowHandle,
 };
@@ -12,14 +11,43 @@ use smithay_client_toolkit::{
         wlr_layer::{Anchor, LayerShell, LayerSurface},
     },
 };
-use std::{borrow::Cow, env::args, ptr::NonNull, str::FromStr, time::Instant};
+use std::{borrow::Cow, env::args, ptr::NonNull, time::Instant};
 use wayland_client::{Connection, Proxy, QueueHandle, globals::registry_queue_init};
 use wgpu::{BindGroup, Buffer, util::DeviceExt};
 
-use crate::color_palette::ColorPalette;
+use crate::{
+    atlas::Atlas,
+    cli::Command,
+    color_palette::ColorPalette,
+    config::{Config, ConfigFile},
+    simulation::Simulation,
+};
 
+mod atlas;
+mod cli;
+mod color;
 mod color_palette;
+mod config;
+mod edge;
+mod emitter;
+mod glyph;
+mod harmony;
 mod implementations;
+mod origin;
+mod palette_file;
+mod palette_image;
+mod palette_preview;
+mod pile;
+mod range;
+mod shape;
+mod sherlock_theme;
+mod simulation;
+mod sprite;
+mod weighted;
+mod wind;
+
+/// Edge length of a particle in logical pixels at a `size` of 1.
+const PARTICLE_SIZE: f32 = 10.0;
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -61,25 +89,97 @@ impl Vertex {
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 struct InstanceData {
-    direction: [f32; 2],
+    /// Particle center in logical pixels from the top left corner.
+    position: [f32; 2],
+    color: [f32; 3],
+    alpha: f32,
+    /// Random value for per-particle variation in the shader.
+    seed: u32,
+    /// `Shape::id` of the outline drawn in the fragment shader.
+    shape: u32,
+    /// Unit vector the particle tumbles around.
+    axis: [f32; 3],
+    /// Tumbling angle around `axis` in radians.
+    tumble: f32,
+    /// Multiplier for the quad size.
+    size: f32,
+    /// Rotation in the particle's plane in radians, applied before tumbling.
+    rotation: f32,
+}
+
+/// A vertex of a ribbon's triangle strip.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct RibbonVertex {
+    /// Position in logical pixels from the top left corner.
+    position: [f32; 2],
     color: [f32; 3],
+    alpha: f32,
+    /// OKLab lightness offset, darkens the back of the ribbon.
+    shade: f32,
 }
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 struct Uniforms {
     time: f32,
+    /// Keeps `resolution` aligned like the `vec2` in the shader.
+    _padding: f32,
+    /// Surface size in logical pixels, set in `configure`.
+    resolution: [f32; 2],
 }
 
 impl Uniforms {
     fn new() -> Self {
-        Self { time: 0.0 }
+        Self {
+            time: 0.0,
+            _padding: 0.0,
+            resolution: [256.0, 256.0],
+        }
     }
 }
 
 fn main() {
     env_logger::init();
 
+    let cli = cli::parse(args().skip(1)).unwrap_or_else(|err| fail(&err));
+    match cli.command {
+        Command::Help => {
+            print!("{}", cli::HELP);
+            return;
+        }
+        Command::Version => {
+            println!("confetti {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Command::Run | Command::ListPalettes | Command::Palettes { .. } => {}
+    }
+
+    let file = ConfigFile::load().unwrap_or_else(|err| fail(&err));
+    color_palette::register_user_palettes(&file.palettes);
+    if let Command::ListPalettes = cli.command {
+        for name in ColorPalette::NAMES {
+            println!("{}", name);
+        }
+        for name in color_palette::user_palette_names() {
+            println!("{}", name);
+        }
+        return;
+    }
+    if let Command::Palettes { png } = &cli.command {
+        let palettes = palette_preview::all_palettes();
+        match png {
+            Some(path) => {
+                palette_preview::render_png(&palettes, path).unwrap_or_else(|err| fail(&err))
+            }
+            None => palette_preview::print_swatches(&palettes).unwrap_or_else(|err| fail(&err)),
+        }
+        return;
+    }
+    let config = Config::resolve(&file, cli.profile.as_deref(), &cli.overrides)
+        .unwrap_or_else(|err| fail(&err));
+    let atlas = Atlas::new(&config).unwrap_or_else(|err| fail(&err));
+
     let conn = Connection::connect_to_env().unwrap();
     let (globals, mut event_queue) = registry_queue_init(&conn).unwrap();
     let qh = event_queue.handle();
@@ -94,12 +194,11 @@ fn main() {
     let layer = layer_state.create_layer_surface(
         &qh,
         surface,
-        smithay_client_toolkit::shell::wlr_layer::Layer::Top,
-        Some(""),
+        config.layer.into(),
+        Some(config.namespace.as_str()),
         None,
     );
     layer.set_anchor(Anchor::TOP | Anchor::BOTTOM | Anchor::LEFT | Anchor::RIGHT);
-    let (width, height) = (400, 400);
     layer.set_size(0, 0); // 0 width = stretch to full width
     layer.set_opaque_region(None);
     layer.commit();
@@ -137,15 +236,20 @@ fn main() {
     let (device, queue) = pollster::block_on(adapter.request_device(&Default::default()))
         .expect("Failed to request device");
 
-    let mut surface_config = surface.get_default_config(&adapter, 200, 200).unwrap();
-    surface_config.alpha_mode = wgpu::CompositeAlphaMode::PreMultiplied;
-    surface_config.format = wgpu::TextureFormat::Bgra8Unorm;
+    let surface_config = create_surface_config(&surface, &adapter);
 
     let (layout, group, uniform_buffer, uniforms) = create_uniforms(&device);
-    let (vertex_buffer, instance_buffer, vertex_count, instance_count) =
-        create_vertex_buffer(&device, width as f32, height as f32);
-
-    let render_pipeline = create_pipeline(&device, surface_config.format, &layout);
+    let (vertex_buffer, vertex_count) = create_vertex_buffer(&device, &config);
+    let (atlas_layout, atlas_group) = create_atlas(&device, &queue, &atlas);
+    let simulation = Simulation::new(&config, &atlas.shapes);
+    let instance_capacity = config.count as usize;
+    let instance_buffer = create_instance_buffer(&device, instance_capacity);
+    let ribbon_capacity = config.ribbons as usize * simulation::RIBBON_VERTICES;
+    let ribbon_buffer = create_ribbon_buffer(&device, ribbon_capacity);
+
+    let layouts = [&layout, &atlas_layout];
+    let render_pipeline = create_pipeline(&device, &surface_config, &layouts);
+    let ribbon_pipeline = create_ribbon_pipeline(&device, &surface_config, &layouts);
 
     let mut wgpu = Wgpu {
         registry_state: RegistryState::new(&globals),
@@ -153,6 +257,7 @@ fn main() {
         output_state: OutputState::new(&globals, &qh),
 
         start_time: Instant::now(),
+        duration: config.duration,
         first_configure: true,
         exit: false,
         width: 256,
@@ -160,16 +265,22 @@ fn main() {
         window: layer,
         device,
         surface,
+        surface_config,
         queue,
         render_pipeline,
+        ribbon_pipeline,
         group,
+        atlas_group,
 
         uniforms,
         uniform_buffer,
         vertex_buffer,
         instance_buffer,
+        instance_capacity,
+        ribbon_buffer,
+        ribbon_capacity,
         vertex_count,
-        instance_count,
+        simulation,
     };
 
     // We don't draw immediately, the configure will notify us when to first draw.
@@ -185,12 +296,21 @@ fn main() {
     drop(wgpu.surface);
     drop(wgpu.window);
 }
+/// Reports invalid input, from the command line as well as the config file,
+/// and exits.
+fn fail(err: &str) -> ! {
+    eprintln!("confetti: {}", err);
+    eprintln!("Try 'confetti --help' for more information.");
+    std::process::exit(2);
+}
+
 struct Wgpu {
     registry_state: RegistryState,
     seat_state: SeatState,
     output_state: OutputState,
 
     start_time: Instant,
+    duration: Option<f32>,
     exit: bool,
     first_configure: bool,
     width: u32,
@@ -200,24 +320,55 @@ struct Wgpu {
     device: wgpu::Device,
     queue: wgpu::Queue,
     surface: wgpu::Surface<'static>,
+    surface_config: wgpu::SurfaceConfiguration,
     render_pipeline: wgpu::RenderPipeline,
+    ribbon_pipeline: wgpu::RenderPipeline,
     group: wgpu::BindGroup,
+    /// Texture and sampler for glyph and sprite particles.
+    atlas_group: wgpu::BindGroup,
 
     uniforms: Uniforms,
     uniform_buffer: Buffer,
     vertex_buffer: Buffer,
     instance_buffer: Buffer,
+    /// Number of instances that fit into `instance_buffer`.
+    instance_capacity: usize,
+    ribbon_buffer: Buffer,
+    /// Number of vertices that fit into `ribbon_buffer`.
+    ribbon_capacity: usize,
     vertex_count: u32,
-    instance_count: u32,
+    simulation: Simulation,
 }
 
 impl Wgpu {
     fn draw(&mut self, _qh: &QueueHandle<Self>) {
         let elapsed = self.start_time.elapsed().as_secs_f32();
         self.update_time(elapsed);
-        if elapsed > 3.0 {
+        self.simulation.advance(elapsed);
+        let timed_out = self.duration.is_some_and(|duration| elapsed > duration);
+        if timed_out || self.simulation.is_finished() {
             self.exit = true
         }
+
+        let instances = self.simulation.instances();
+        if instances.len() > self.instance_capacity {
+            self.instance_capacity = instances.len().next_power_of_two();
+            self.instance_buffer = create_instance_buffer(&self.device, self.instance_capacity);
+        }
+        self.queue
+            .write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+
+        let ribbon_vertices = self.simulation.ribbon_vertices();
+        if ribbon_vertices.len() > self.ribbon_capacity {
+            self.ribbon_capacity = ribbon_vertices.len().next_power_of_two();
+            self.ribbon_buffer = create_ribbon_buffer(&self.device, self.ribbon_capacity);
+        }
+        self.queue.write_buffer(
+            &self.ribbon_buffer,
+            0,
+            bytemuck::cast_slice(&ribbon_vertices),
+        );
+
         let surface_texture = self
             .surface
             .get_current_texture()
@@ -245,10 +396,18 @@ impl Wgpu {
                 occlusion_query_set: None,
             });
             rpass.set_bind_group(0, &self.group, &[]);
+            rpass.set_bind_group(1, &self.atlas_group, &[]);
             rpass.set_pipeline(&self.render_pipeline);
             rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
             rpass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-            rpass.draw(0..self.vertex_count, 0..self.instance_count);
+            rpass.draw(0..self.vertex_count, 0..instances.len() as u32);
+
+            rpass.set_pipeline(&self.ribbon_pipeline);
+            rpass.set_vertex_buffer(0, self.ribbon_buffer.slice(..));
+            for start in (0..ribbon_vertices.len()).step_by(simulation::RIBBON_VERTICES) {
+                let start = start as u32;
+                rpass.draw(start..start + simulation::RIBBON_VERTICES as u32, 0..1);
+            }
         }
         self.queue.submit(Some(encoder.finish()));
         surface_texture.present();
@@ -260,11 +419,71 @@ impl Wgpu {
     }
 }
 
+/// Picks an sRGB format and a compositor alpha mode that supports
+/// translucency, if the surface offers them.
+fn create_surface_config(
+    surface: &wgpu::Surface,
+    adapter: &wgpu::Adapter,
+) -> wgpu::SurfaceConfiguration {
+    let capabilities = surface.get_capabilities(adapter);
+    let format = capabilities
+        .formats
+        .iter()
+        .copied()
+        .find(wgpu::TextureFormat::is_srgb)
+        .unwrap_or(capabilities.formats[0]);
+    let alpha_mode = [
+        wgpu::CompositeAlphaMode::PreMultiplied,
+        wgpu::CompositeAlphaMode::PostMultiplied,
+        wgpu::CompositeAlphaMode::Inherit,
+    ]
+    .into_iter()
+    .find(|mode| capabilities.alpha_modes.contains(mode))
+    .unwrap_or(capabilities.alpha_modes[0]);
+
+    wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format,
+        width: 256,
+        height: 256,
+        present_mode: wgpu::PresentMode::Fifo,
+        alpha_mode,
+        view_formats: vec![],
+        desired_maximum_frame_latency: 2,
+    }
+}
+
+/// Returns the shader constants and the blend state for the surface. Colors
+/// are blended in linear space on sRGB surfaces, otherwise the shader encodes
+/// them itself. Only straight alpha surfaces get non-premultiplied output.
+fn output_settings(
+    surface_config: &wgpu::SurfaceConfiguration,
+) -> ([(&'static str, f64); 2], wgpu::BlendState) {
+    let srgb_surface = surface_config.format.is_srgb();
+    let premultiplied = surface_config.alpha_mode != wgpu::CompositeAlphaMode::PostMultiplied;
+    let constants = [
+        ("SRGB_SURFACE", srgb_surface as u32 as f64),
+        ("PREMULTIPLIED", premultiplied as u32 as f64),
+    ];
+    let blend = if premultiplied {
+        wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING
+    } else {
+        wgpu::BlendState::ALPHA_BLENDING
+    };
+    (constants, blend)
+}
+
 fn create_pipeline(
     device: &wgpu::Device,
-    swap_chain_format: wgpu::TextureFormat,
-    bind_group_layout: &wgpu::BindGroupLayout,
+    surface_config: &wgpu::SurfaceConfiguration,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
 ) -> wgpu::RenderPipeline {
+    let (constants, blend) = output_settings(surface_config);
+    let compilation_options = wgpu::PipelineCompilationOptions {
+        constants: &constants,
+        ..Default::default()
+    };
+
     // Load theushaders from disk
     let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
         label: None,
@@ -272,7 +491,7 @@ fn create_pipeline(
     });
     let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("Render Pipeline Layout"),
-        bind_group_layouts: &[bind_group_layout],
+        bind_group_layouts,
         push_constant_ranges: &[],
     });
     let vertex_buffer_layout = wgpu::VertexBufferLayout {
@@ -289,7 +508,7 @@ fn create_pipeline(
         step_mode: wgpu::VertexStepMode::Instance,
         attributes: &[
             wgpu::VertexAttribute {
-                format: wgpu::VertexFormat::Float32x2, // v_start
+                format: wgpu::VertexFormat::Float32x2, // position
                 offset: 0,
                 shader_location: 1,
             },
@@ -298,6 +517,41 @@ fn create_pipeline(
                 offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
                 shader_location: 2,
             },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32, // alpha
+                offset: std::mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                shader_location: 3,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Uint32, // seed
+                offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                shader_location: 4,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Uint32, // shape
+                offset: std::mem::size_of::<[f32; 7]>() as wgpu::BufferAddress,
+                shader_location: 5,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x3, // axis
+                offset: std::mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                shader_location: 6,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32, // tumble
+                offset: std::mem::size_of::<[f32; 11]>() as wgpu::BufferAddress,
+                shader_location: 7,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32, // size
+                offset: std::mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                shader_location: 8,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32, // rotation
+                offset: std::mem::size_of::<[f32; 13]>() as wgpu::BufferAddress,
+                shader_location: 9,
+            },
         ],
     };
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -307,15 +561,15 @@ fn create_pipeline(
             module: &shader,
             entry_point: Some("vs_main"),
             buffers: &[vertex_buffer_layout, instance_buffer_layout],
-            compilation_options: Default::default(),
+            compilation_options: compilation_options.clone(),
         },
         fragment: Some(wgpu::FragmentState {
             module: &shader,
             entry_point: Some("fs_main"),
-            compilation_options: Default::default(),
+            compilation_options,
             targets: &[Some(wgpu::ColorTargetState {
-                format: swap_chain_format,
-                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                format: surface_config.format,
+                blend: Some(blend),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
         }),
@@ -340,6 +594,84 @@ fn create_pipeline(
     })
 }
 
+/// Creates the pipeline for ribbons, which are drawn as one triangle strip
+/// per ribbon from the vertices built by the simulation.
+fn create_ribbon_pipeline(
+    device: &wgpu::Device,
+    surface_config: &wgpu::SurfaceConfiguration,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+) -> wgpu::RenderPipeline {
+    let (constants, blend) = output_settings(surface_config);
+    let compilation_options = wgpu::PipelineCompilationOptions {
+        constants: &constants,
+        ..Default::default()
+    };
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Ribbon Pipeline Layout"),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+    let ribbon_buffer_layout = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<RibbonVertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &[
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x2, // position
+                offset: 0,
+                shader_location: 0,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x3, // color
+                offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                shader_location: 1,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32, // alpha
+                offset: std::mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                shader_location: 2,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32, // shade
+                offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                shader_location: 3,
+            },
+        ],
+    };
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Ribbon Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_ribbon"),
+            buffers: &[ribbon_buffer_layout],
+            compilation_options: compilation_options.clone(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            compilation_options,
+            targets: &[Some(wgpu::ColorTargetState {
+                format: surface_config.format,
+                blend: Some(blend),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
 fn create_uniforms(device: &wgpu::Device) -> (wgpu::BindGroupLayout, BindGroup, Buffer, Uniforms) {
     let uniforms = Uniforms::new();
     let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -380,33 +712,105 @@ fn create_uniforms(device: &wgpu::Device) -> (wgpu::BindGroupLayout, BindGroup,
     )
 }
 
-fn create_vertex_buffer(
+/// Uploads the atlas with a full mip chain, so images stay smooth when they
+/// are drawn much smaller than a cell.
+fn create_atlas(
     device: &wgpu::Device,
-    width: f32,
-    height: f32,
-) -> (Buffer, Buffer, u32, u32) {
-    // Only 1 rectangle vertices here, since instances define position:
-    let rectangle = Vertex::rectangle(0.0, 0.0, 0.00002 * height, 0.00002 * width);
-    let args = args().collect::<Vec<String>>();
-    let pallette = extreact_flag_value::<ColorPalette>(&args, "--pallette").unwrap_or_default();
-    let colors = pallette.get_colors();
-    let color_count = colors.len();
-
-    let mut rng = rand::rng();
-    let instances = (0..200)
-        .map(|_| {
-            let x = rng.random_range(-1.0..1.0) as f32;
-            let y_max = (1.0 - x * x).sqrt() * 2.5;
-            let y = rng.random_range(-0.5..y_max);
-            InstanceData {
-                direction: [x * 1.2, y],
-                color: colors
-                    .get(rng.random_range(0..color_count))
-                    .unwrap()
-                    .clone(),
-            }
-        })
-        .collect::<Vec<InstanceData>>();
+    queue: &wgpu::Queue,
+    atlas: &Atlas,
+) -> (wgpu::BindGroupLayout, BindGroup) {
+    let levels = atlas.mip_levels();
+    let (width, height) = levels[0].dimensions();
+    let mip_level_count = levels.len() as u32;
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Atlas Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    for (level, mip) in levels.iter().enumerate() {
+        let (w, h) = mip.dimensions();
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: level as u32,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            mip,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * w),
+                rows_per_image: Some(h),
+            },
+            wgpu::Extent3d {
+                width: w,
+                height: h,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Atlas Sampler"),
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
+
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+        label: Some("atlas_bind_group_layout"),
+    });
+    let group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+        ],
+        label: Some("atlas_bind_group"),
+    });
+    (layout, group)
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, config: &Config) -> (Buffer, u32) {
+    // Only 1 rectangle vertices here, since instances define position.
+    // Centered on the particle, the shader derives the shape coordinates
+    // from the sign of each corner.
+    let side = PARTICLE_SIZE * config.size;
+    let rectangle = Vertex::rectangle(-side / 2.0, -side / 2.0, side, side);
 
     let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
         label: Some("Rectangle Vertex Buffer"),
@@ -414,25 +818,25 @@ fn create_vertex_buffer(
         usage: wgpu::BufferUsages::VERTEX,
     });
 
-    let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some("Instance Buffer"),
-        contents: bytemuck::cast_slice(&instances),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
+    (vertex_buffer, rectangle.len() as u32)
+}
 
-    (
-        vertex_buffer,
-        instance_buffer,
-        rectangle.len() as u32,
-        instances.len() as u32,
-    )
+/// Creates a buffer for `capacity` instances, rewritten every frame.
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Instance Buffer"),
+        size: (capacity * std::mem::size_of::<InstanceData>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
 }
 
-fn extreact_flag_value<T: FromStr>(args: &Vec<String>, name: &str) -> Option<T> {
-    let pos = args.iter().position(|arg| arg == name)?;
-    let val = args.get(pos + 1)?;
-    if val.starts_with("-") {
-        return None;
-    }
-    val.parse::<T>().ok()
+/// Creates a buffer for `capacity` ribbon vertices, rewritten every frame.
+fn create_ribbon_buffer(device: &wgpu::Device, capacity: usize) -> Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Ribbon Vertex Buffer"),
+        size: (capacity.max(1) * std::mem::size_of::<RibbonVertex>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
 }
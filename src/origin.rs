@@ -0,0 +1,212 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// A position along one screen axis.
+#[derive(Debug, Clone, Copy)]
+pub enum Coordinate {
+    /// Fraction of the screen size, `0.0` is the left or top edge.
+    Fraction(f32),
+    /// Logical pixels from the left or top edge.
+    Pixels(f32),
+    /// Logical pixels from the right or bottom edge, written with a leading
+    /// `-`, so `-0px` is the edge itself.
+    PixelsFromEnd(f32),
+}
+
+impl Coordinate {
+    fn anchor(self) -> (f32, f32) {
+        match self {
+            Self::Fraction(fraction) => (fraction, 0.0),
+            Self::Pixels(pixels) => (0.0, pixels),
+            Self::PixelsFromEnd(pixels) => (1.0, -pixels),
+        }
+    }
+}
+
+impl FromStr for Coordinate {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (number, pixels) = match s.strip_suffix("px") {
+            Some(number) => (number, true),
+            None => (s, false),
+        };
+        let value = number
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| format!("Invalid coordinate '{}'", s))?;
+        Ok(match (pixels, number.trim().starts_with('-')) {
+            (true, true) => Self::PixelsFromEnd(-value),
+            (true, false) => Self::Pixels(value),
+            (false, _) => Self::Fraction(value),
+        })
+    }
+}
+
+/// Point the confetti is launched from, written as `X,Y` with fractions of
+/// the screen or pixel values like `40px,-40px`, or as a named position.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(try_from = "OriginEntry")]
+pub struct Origin {
+    pub x: Coordinate,
+    pub y: Coordinate,
+}
+
+impl Default for Origin {
+    fn default() -> Self {
+        Self::fraction(0.5, 0.5)
+    }
+}
+
+impl Origin {
+    pub const NAMES: [&'static str; 9] = [
+        "top-left",
+        "top",
+        "top-right",
+        "left",
+        "center",
+        "right",
+        "bottom-left",
+        "bottom",
+        "bottom-right",
+    ];
+
+    pub const fn fraction(x: f32, y: f32) -> Self {
+        Self {
+            x: Coordinate::Fraction(x),
+            y: Coordinate::Fraction(y),
+        }
+    }
+
+    /// Splits the origin into a fraction of the screen and an offset in
+    /// pixels, which are combined once the surface size is known.
+    pub fn anchor(&self) -> ([f32; 2], [f32; 2]) {
+        let (x, offset_x) = self.x.anchor();
+        let (y, offset_y) = self.y.anchor();
+        ([x, y], [offset_x, offset_y])
+    }
+
+    fn named(name: &str) -> Option<Self> {
+        let (x, y) = match name {
+            "top-left" => (0.0, 0.0),
+            "top" | "top-center" => (0.5, 0.0),
+            "top-right" => (1.0, 0.0),
+            "left" | "center-left" => (0.0, 0.5),
+            "center" => (0.5, 0.5),
+            "right" | "center-right" => (1.0, 0.5),
+            "bottom-left" => (0.0, 1.0),
+            "bottom" | "bottom-center" => (0.5, 1.0),
+            "bottom-right" => (1.0, 1.0),
+            _ => return None,
+        };
+        Some(Self::fraction(x, y))
+    }
+}
+
+impl FromStr for Origin {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(origin) = Self::named(&s.trim().to_lowercase()) {
+            return Ok(origin);
+        }
+        let (x, y) = s.split_once(',').ok_or_else(|| {
+            format!(
+                "Invalid origin '{}', expected X,Y or one of {}",
+                s,
+                Self::NAMES.join(", ")
+            )
+        })?;
+        Ok(Self {
+            x: x.parse()?,
+            y: y.parse()?,
+        })
+    }
+}
+
+/// An origin in the config file: either `[x, y]` fractions or a string as
+/// accepted by `Origin::from_str`.
+#[derive(Deserialize)]
+#[serde(
+    untagged,
+    expecting = "expected an origin as [x, y] or a string like \"bottom-left\" or \"40px,-40px\""
+)]
+enum OriginEntry {
+    Fraction([f32; 2]),
+    Spec(String),
+}
+
+impl TryFrom<OriginEntry> for Origin {
+    type Error = String;
+    fn try_from(entry: OriginEntry) -> Result<Self, Self::Error> {
+        match entry {
+            OriginEntry::Fraction([x, y]) if x.is_finite() && y.is_finite() => {
+                Ok(Self::fraction(x, y))
+            }
+            OriginEntry::Fraction(_) => Err("origin must be finite".to_string()),
+            OriginEntry::Spec(spec) => spec.parse(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anchor(s: &str) -> ([f32; 2], [f32; 2]) {
+        s.parse::<Origin>().unwrap().anchor()
+    }
+
+    #[test]
+    fn parses_named_origins() {
+        assert_eq!(anchor("bottom-left"), ([0.0, 1.0], [0.0, 0.0]));
+        assert_eq!(anchor(" Top "), ([0.5, 0.0], [0.0, 0.0]));
+        assert_eq!(anchor("center-right"), ([1.0, 0.5], [0.0, 0.0]));
+        assert_eq!(Origin::default().anchor(), ([0.5, 0.5], [0.0, 0.0]));
+    }
+
+    #[test]
+    fn parses_coordinates() {
+        assert_eq!(anchor("0.25, 0.75"), ([0.25, 0.75], [0.0, 0.0]));
+        assert_eq!(anchor("40px,-40px"), ([0.0, 1.0], [40.0, -40.0]));
+        assert_eq!(anchor("-10 px, 0.5"), ([1.0, 0.5], [-10.0, 0.0]));
+    }
+
+    #[test]
+    fn negative_zero_pixels_is_the_far_edge() {
+        assert_eq!(anchor("-0px,-0px"), ([1.0, 1.0], [0.0, 0.0]));
+        assert_eq!(anchor("0px,0px"), ([0.0, 0.0], [0.0, 0.0]));
+    }
+
+    #[test]
+    fn rejects_invalid_origins() {
+        let error = |s: &str| s.parse::<Origin>().unwrap_err();
+        assert_eq!(
+            error("middle"),
+            "Invalid origin 'middle', expected X,Y or one of top-left, top, top-right, \
+             left, center, right, bottom-left, bottom, bottom-right"
+        );
+        assert_eq!(error("0.5,abc"), "Invalid coordinate 'abc'");
+        assert_eq!(error("inf,0"), "Invalid coordinate 'inf'");
+        assert_eq!(error("10em,0"), "Invalid coordinate '10em'");
+    }
+
+    #[test]
+    fn converts_config_entries() {
+        let anchor = |entry| Origin::try_from(entry).map(|origin| origin.anchor());
+        assert_eq!(
+            anchor(OriginEntry::Fraction([0.1, 0.2])),
+            Ok(([0.1, 0.2], [0.0, 0.0]))
+        );
+        assert_eq!(
+            anchor(OriginEntry::Spec("0,20px".to_string())),
+            Ok(([0.0, 0.0], [0.0, 20.0]))
+        );
+        assert_eq!(
+            anchor(OriginEntry::Fraction([f32::NAN, 0.0])),
+            Err("origin must be finite".to_string())
+        );
+    }
+}
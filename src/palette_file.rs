@@ -0,0 +1,331 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{color, color_palette::ColorVec};
+
+/// Colors closer than this OKLab distance to the background are dropped when
+/// filtering is enabled.
+const BACKGROUND_DISTANCE: f32 = 0.15;
+
+/// Colors and background read from a theme file.
+#[derive(Debug)]
+struct Theme {
+    colors: ColorVec,
+    background: Option<[f32; 3]>,
+}
+
+/// Loads the colors of a pywal `colors.json`, base16/base24 scheme,
+/// Xresources or GIMP `.gpl` file. The format is detected from the content.
+pub fn load(path: &Path, filter_background: bool) -> Result<ColorVec, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let theme = parse(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let mut colors = theme.colors;
+    if filter_background && let Some(background) = theme.background {
+        colors.retain(|c| color::distance(*c, background) >= BACKGROUND_DISTANCE);
+    }
+    if colors.is_empty() {
+        return Err(format!("{}: no usable colors found", path.display()));
+    }
+    Ok(colors)
+}
+
+/// Detects the format of a theme file and parses it.
+fn parse(content: &str) -> Result<Theme, String> {
+    if content.trim_start().starts_with("GIMP Palette") {
+        parse_gpl(content)
+    } else if content.trim_start().starts_with('{') {
+        parse_pywal(content)
+    } else if content.lines().any(|line| base16_entry(line).is_some()) {
+        parse_base16(content)
+    } else if content.lines().any(|line| xresources_entry(line).is_some()) {
+        parse_xresources(content)
+    } else {
+        Err(
+            "unrecognized palette format, expected pywal, base16, Xresources or GIMP palette"
+                .to_string(),
+        )
+    }
+}
+
+/// Parses a pywal `colors.json`.
+fn parse_pywal(content: &str) -> Result<Theme, String> {
+    let json = serde_json::from_str::<serde_json::Value>(content).map_err(|e| e.to_string())?;
+
+    let entries = json
+        .get("colors")
+        .and_then(|c| c.as_object())
+        .ok_or("missing \"colors\" object")?;
+    let mut indexed = entries
+        .iter()
+        .filter_map(|(key, value)| {
+            let index = key.strip_prefix("color")?.parse::<u32>().ok()?;
+            Some((index, value.as_str()?))
+        })
+        .collect::<Vec<_>>();
+    indexed.sort_unstable_by_key(|(index, _)| *index);
+
+    let colors = indexed
+        .into_iter()
+        .map(|(_, value)| color::parse_color(value))
+        .collect::<Result<ColorVec, String>>()?;
+    let background = json
+        .pointer("/special/background")
+        .and_then(|c| c.as_str())
+        .map(color::parse_color)
+        .transpose()?;
+    Ok(Theme { colors, background })
+}
+
+/// Returns the slot and value of a `baseXX: "value"` line.
+fn base16_entry(line: &str) -> Option<(u8, &str)> {
+    let (key, value) = line.trim().split_once(':')?;
+    let slot = u8::from_str_radix(key.trim().strip_prefix("base")?, 16).ok()?;
+    let value = value.trim();
+    // Either a quoted string or a bare value followed by an optional comment
+    let value = match value.chars().next()? {
+        quote @ ('"' | '\'') => value[1..].split(quote).next()?,
+        _ => value.split_whitespace().next()?,
+    };
+    Some((slot, value.trim_start_matches('#')))
+}
+
+/// Parses a base16 or base24 YAML scheme, in both the classic flat layout
+/// and the newer layout with a `palette` section. Only the accent slots are
+/// used, the `base00` to `base07` ramp is made of background and text shades.
+fn parse_base16(content: &str) -> Result<Theme, String> {
+    let slots = content
+        .lines()
+        .filter_map(base16_entry)
+        .map(|(slot, value)| Ok((slot, color::parse_color(&format!("#{}", value))?)))
+        .collect::<Result<HashMap<u8, [f32; 3]>, String>>()?;
+
+    // base08-0F are the base16 accents, base12-17 the bright base24 accents
+    let colors = (0x08..=0x0f)
+        .chain(0x12..=0x17)
+        .filter_map(|slot| slots.get(&slot).copied())
+        .collect();
+    Ok(Theme {
+        colors,
+        background: slots.get(&0x00).copied(),
+    })
+}
+
+/// Returns the key and value of an Xresources color resource, e.g.
+/// `*.color4: #81a2be`. The key is either a color index or `background`.
+fn xresources_entry(line: &str) -> Option<(Option<u32>, &str)> {
+    let line = line.trim();
+    if line.starts_with('!') || line.starts_with('#') {
+        return None;
+    }
+    let (key, value) = line.split_once(':')?;
+    let name = key.trim().rsplit(['*', '.']).next()?;
+    let index = match name.strip_prefix("color") {
+        Some(index) => Some(index.parse::<u32>().ok()?),
+        None if name == "background" => None,
+        None => return None,
+    };
+    Some((index, value.trim()))
+}
+
+/// Parses the 16 terminal colors from an Xresources file. Simple `#define`
+/// macros are substituted.
+fn parse_xresources(content: &str) -> Result<Theme, String> {
+    let defines = content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.trim().strip_prefix("#define")?.split_whitespace();
+            Some((parts.next()?, parts.next()?))
+        })
+        .collect::<HashMap<_, _>>();
+    let parse = |value: &str| {
+        let value = defines.get(value).copied().unwrap_or(value);
+        parse_x11_color(value)
+    };
+
+    let mut indexed = Vec::new();
+    let mut background = None;
+    for (index, value) in content.lines().filter_map(xresources_entry) {
+        match index {
+            Some(index) if index < 16 => indexed.push((index, parse(value)?)),
+            Some(_) => {}
+            None => background = Some(parse(value)?),
+        }
+    }
+    // Later definitions override earlier ones
+    indexed.reverse();
+    indexed.sort_by_key(|(index, _)| *index);
+    indexed.dedup_by_key(|(index, _)| *index);
+
+    let colors = indexed.into_iter().map(|(_, c)| c).collect::<ColorVec>();
+    let background = background.or_else(|| colors.first().copied());
+    Ok(Theme { colors, background })
+}
+
+/// Parses `#rrggbb` as well as the X11 `rgb:rr/gg/bb` notation.
+fn parse_x11_color(value: &str) -> Result<[f32; 3], String> {
+    let Some(channels) = value.strip_prefix("rgb:") else {
+        return color::parse_color(value);
+    };
+    let channels = channels
+        .split('/')
+        .map(|c| {
+            if !(1..=4).contains(&c.len()) {
+                return None;
+            }
+            let max = 16u32.pow(c.len() as u32) - 1;
+            u32::from_str_radix(c, 16)
+                .ok()
+                .map(|v| v as f32 / max as f32)
+        })
+        .collect::<Option<Vec<f32>>>();
+    match channels.as_deref() {
+        Some(&[r, g, b]) => Ok([r, g, b]),
+        _ => Err(format!("Invalid X11 color '{}'", value)),
+    }
+}
+
+/// Parses a GIMP palette. These carry no background color.
+fn parse_gpl(content: &str) -> Result<Theme, String> {
+    let mut colors = ColorVec::new();
+    for (number, line) in content.lines().enumerate().skip(1) {
+        let line = line.trim();
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("Name:")
+            || line.starts_with("Columns:")
+        {
+            continue;
+        }
+        let channels = line
+            .split_whitespace()
+            .take(3)
+            .map(|c| c.parse::<u8>().ok().map(|v| v as f32 / 255.0))
+            .collect::<Option<Vec<f32>>>();
+        match channels.as_deref() {
+            Some(&[r, g, b]) => colors.push([r, g, b]),
+            _ => return Err(format!("line {}: expected 'R G B [name]'", number + 1)),
+        }
+    }
+    Ok(Theme {
+        colors,
+        background: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(value: &str) -> [f32; 3] {
+        color::parse_color(value).unwrap()
+    }
+
+    #[test]
+    fn parses_pywal() {
+        let theme = parse(
+            r##"{
+                "special": { "background": "#1d1f21" },
+                "colors": {
+                    "color10": "#b5bd68",
+                    "color0": "#1d1f21",
+                    "color1": "#cc6666",
+                    "color2": "#8c9440"
+                }
+            }"##,
+        )
+        .unwrap();
+        assert_eq!(
+            theme.colors,
+            ["#1d1f21", "#cc6666", "#8c9440", "#b5bd68"].map(hex)
+        );
+        assert_eq!(theme.background, Some(hex("#1d1f21")));
+    }
+
+    #[test]
+    fn reports_pywal_errors() {
+        assert_eq!(
+            parse(r#"{"colors": [1,}"#).unwrap_err(),
+            "expected value at line 1 column 15"
+        );
+        assert_eq!(
+            parse(r#"{"wallpaper": "a.png"}"#).unwrap_err(),
+            "missing \"colors\" object"
+        );
+        assert_eq!(
+            parse(r##"{"colors": {"color0": "#12"}}"##).unwrap_err(),
+            "Invalid hex color '#12': expected #rgb or #rrggbb with hex digits"
+        );
+    }
+
+    #[test]
+    fn parses_base16() {
+        let flat = parse(
+            "scheme: \"Tomorrow Night\"\n\
+             base00: \"1d1f21\"\n\
+             base05: \"c5c8c6\"\n\
+             base08: \"cc6666\"\n\
+             base0D: \"81a2be\"\n",
+        )
+        .unwrap();
+        assert_eq!(flat.colors, ["#cc6666", "#81a2be"].map(hex));
+        assert_eq!(flat.background, Some(hex("#1d1f21")));
+
+        let nested = parse(
+            "system: \"base24\"\n\
+             palette:\n  \
+               base00: '#1d1f21'\n  \
+               base08: '#cc6666' # red\n  \
+               base12: '#ff3334'\n",
+        )
+        .unwrap();
+        assert_eq!(nested.colors, ["#cc6666", "#ff3334"].map(hex));
+    }
+
+    #[test]
+    fn parses_xresources() {
+        let theme = parse(
+            "! Tomorrow Night\n\
+             #define red #cc6666\n\
+             *.background: #1d1f21\n\
+             *.color1: red\n\
+             URxvt.color0: rgb:1d/1f/21\n\
+             *color1: #ff0000\n\
+             *.color16: #ffffff\n",
+        )
+        .unwrap();
+        // The later color1 wins and color16 is not a terminal color
+        assert_eq!(theme.colors, ["#1d1f21", "#ff0000"].map(hex));
+        assert_eq!(theme.background, Some(hex("#1d1f21")));
+        assert_eq!(
+            parse("*.color1: rgb:12/34\n").unwrap_err(),
+            "Invalid X11 color 'rgb:12/34'"
+        );
+    }
+
+    #[test]
+    fn parses_gpl() {
+        let theme = parse(
+            "GIMP Palette\n\
+             Name: Test\n\
+             Columns: 4\n\
+             # comment\n\
+             255   0 102\tPink\n\
+             \x20 0 204 255\n",
+        )
+        .unwrap();
+        assert_eq!(theme.colors, ["#ff0066", "#00ccff"].map(hex));
+        assert_eq!(theme.background, None);
+        assert_eq!(
+            parse("GIMP Palette\n255 0\n").unwrap_err(),
+            "line 2: expected 'R G B [name]'"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_formats() {
+        assert_eq!(
+            parse("red, green, blue").unwrap_err(),
+            "unrecognized palette format, expected pywal, base16, Xresources or GIMP palette"
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use std::path::Path;
+
+use image::ImageReader;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{color, color_palette::ColorVec};
+
+/// Images are downscaled to fit into this many pixels per side before
+/// clustering, which is plenty for finding the dominant colors.
+const SAMPLE_SIZE: u32 = 128;
+const ITERATIONS: usize = 24;
+/// Most colors that can be extracted, clustering gets slow beyond that.
+pub const MAX_COLORS: u32 = 64;
+
+/// Extracts `count` dominant colors from a PNG or JPEG image by clustering its
+/// pixels in OKLab. The chroma of the result is scaled by `saturation_boost`.
+pub fn load(path: &Path, count: usize, saturation_boost: f32) -> Result<ColorVec, String> {
+    let image = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| format!("{}: {}", path.display(), e))?
+        .decode()
+        .map_err(|e| format!("{}: {}", path.display(), e))?
+        .thumbnail(SAMPLE_SIZE, SAMPLE_SIZE)
+        .to_rgba8();
+
+    let pixels = image
+        .pixels()
+        .filter(|pixel| pixel[3] >= 128)
+        .map(|pixel| color::srgb_to_oklab([pixel[0], pixel[1], pixel[2]].map(|c| c as f32 / 255.0)))
+        .collect::<Vec<_>>();
+    if pixels.is_empty() {
+        return Err(format!("{}: image has no opaque pixels", path.display()));
+    }
+
+    Ok(kmeans(&pixels, count)
+        .into_iter()
+        .map(|[l, a, b]| color::oklab_to_srgb([l, a * saturation_boost, b * saturation_boost]))
+        .collect())
+}
+
+fn distance_squared(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum()
+}
+
+fn nearest(centers: &[[f32; 3]], point: &[f32; 3]) -> usize {
+    centers
+        .iter()
+        .map(|center| distance_squared(center, point))
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map_or(0, |(i, _)| i)
+}
+
+/// Clusters the points with k-means++ and returns the cluster centers, most
+/// common first. Fewer centers are returned if the image has fewer distinct
+/// colors.
+fn kmeans(points: &[[f32; 3]], count: usize) -> Vec<[f32; 3]> {
+    // A fixed seed keeps the palette stable for the same image
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+    let mut centers = vec![points[rng.random_range(0..points.len())]];
+
+    while centers.len() < count {
+        let weights = points
+            .iter()
+            .map(|p| distance_squared(&centers[nearest(&centers, p)], p))
+            .collect::<Vec<_>>();
+        let total = weights.iter().sum::<f32>();
+        if total <= f32::EPSILON {
+            break;
+        }
+        let mut target = rng.random_range(0.0..total);
+        let index = weights
+            .iter()
+            .position(|w| {
+                target -= w;
+                target <= 0.0
+            })
+            .unwrap_or(points.len() - 1);
+        centers.push(points[index]);
+    }
+
+    let mut sizes = vec![0usize; centers.len()];
+    for _ in 0..ITERATIONS {
+        let mut sums = vec![[0.0f32; 3]; centers.len()];
+        sizes.fill(0);
+        for point in points {
+            let i = nearest(&centers, point);
+            sizes[i] += 1;
+            for (sum, value) in sums[i].iter_mut().zip(point) {
+                *sum += value;
+            }
+        }
+        for ((center, sum), size) in centers.iter_mut().zip(&sums).zip(&sizes) {
+            if *size > 0 {
+                *center = sum.map(|s| s / *size as f32);
+            }
+        }
+    }
+
+    let mut clusters = centers.into_iter().zip(sizes).collect::<Vec<_>>();
+    clusters.retain(|(_, size)| *size > 0);
+    clusters.sort_by(|(_, a), (_, b)| b.cmp(a));
+    clusters.into_iter().map(|(center, _)| center).collect()
+}
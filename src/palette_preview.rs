@@ -0,0 +1,163 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use image::{ImageFormat, Rgb, RgbImage};
+
+use crate::color_palette::{self, ColorPalette, ColorVec};
+
+/// Swatch size and spacing of the rendered sheet, in pixels.
+const SWATCH: u32 = 40;
+const GAP: u32 = 4;
+const PADDING: u32 = 16;
+/// Labels use a 5x7 bitmap font scaled up by this factor.
+const FONT_SCALE: u32 = 2;
+const BACKGROUND: Rgb<u8> = Rgb([30, 30, 30]);
+const TEXT: Rgb<u8> = Rgb([220, 220, 220]);
+
+/// Collects the built-in palettes followed by the ones from the config file.
+pub fn all_palettes() -> Vec<(&'static str, ColorVec)> {
+    ColorPalette::NAMES
+        .into_iter()
+        .chain(color_palette::user_palette_names())
+        .filter_map(|name| Some((name, ColorPalette::from_str(name).ok()?.get_colors())))
+        .collect()
+}
+
+/// Prints one line of truecolor swatches per palette. Stops quietly when
+/// stdout is closed early, like when piped into `head`.
+pub fn print_swatches(palettes: &[(&str, ColorVec)]) -> Result<(), String> {
+    let width = palettes
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut stdout = io::stdout().lock();
+    for (name, colors) in palettes {
+        let swatches = colors
+            .iter()
+            .map(|c| {
+                let [r, g, b] = to_rgb8(*c);
+                format!("\x1b[48;2;{};{};{}m   \x1b[0m", r, g, b)
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        match writeln!(stdout, "{:width$}  {}", name, swatches, width = width) {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            result => result.map_err(|e| format!("Failed to print the palettes: {}", e))?,
+        }
+    }
+    Ok(())
+}
+
+/// Renders the palettes as a labelled grid, one row per palette.
+pub fn render_png(palettes: &[(&str, ColorVec)], path: &Path) -> Result<(), String> {
+    let advance = 6 * FONT_SCALE;
+    let label_width = palettes
+        .iter()
+        .map(|(name, _)| name.chars().count() as u32 * advance)
+        .max()
+        .unwrap_or(0);
+    let columns = palettes
+        .iter()
+        .map(|(_, c)| c.len() as u32)
+        .max()
+        .unwrap_or(0);
+    let rows = palettes.len() as u32;
+
+    let width = PADDING * 3 + label_width + (columns * (SWATCH + GAP)).saturating_sub(GAP);
+    let height = PADDING * 2 + (rows * (SWATCH + GAP * 2)).saturating_sub(GAP * 2);
+    let mut image = RgbImage::from_pixel(width.max(1), height.max(1), BACKGROUND);
+
+    for (row, (name, colors)) in palettes.iter().enumerate() {
+        let y = PADDING + row as u32 * (SWATCH + GAP * 2);
+        draw_text(&mut image, name, PADDING, y + (SWATCH - 7 * FONT_SCALE) / 2);
+        for (column, color) in colors.iter().enumerate() {
+            let x = PADDING * 2 + label_width + column as u32 * (SWATCH + GAP);
+            fill_rect(&mut image, x, y, SWATCH, SWATCH, Rgb(to_rgb8(*color)));
+        }
+    }
+
+    image
+        .save_with_format(path, ImageFormat::Png)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn to_rgb8(color: [f32; 3]) -> [u8; 3] {
+    color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+fn fill_rect(image: &mut RgbImage, x: u32, y: u32, width: u32, height: u32, color: Rgb<u8>) {
+    for py in y..(y + height).min(image.height()) {
+        for px in x..(x + width).min(image.width()) {
+            image.put_pixel(px, py, color);
+        }
+    }
+}
+
+fn draw_text(image: &mut RgbImage, text: &str, x: u32, y: u32) {
+    for (i, c) in text.chars().enumerate() {
+        let origin = x + i as u32 * 6 * FONT_SCALE;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..5 {
+                if bits & (0b10000 >> column) != 0 {
+                    let px = origin + column * FONT_SCALE;
+                    let py = y + row as u32 * FONT_SCALE;
+                    fill_rect(image, px, py, FONT_SCALE, FONT_SCALE, TEXT);
+                }
+            }
+        }
+    }
+}
+
+/// Rows of a 5x7 glyph, most significant bit on the left. Letters are drawn
+/// in uppercase, unsupported characters as a question mark.
+#[rustfmt::skip]
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+        'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+        'D' => [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110],
+        'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+        'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+        'G' => [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+        'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'J' => [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+        'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+        'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+        'N' => [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'P' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+        'Q' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'S' => [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+        'X' => [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+        'Y' => [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100],
+        'Z' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+        '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        '-' => [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000],
+        '_' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111],
+        '.' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100],
+        ':' => [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000],
+        ' ' => [0; 7],
+        _ => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100],
+    }
+}
@@ -0,0 +1,157 @@
+use std::{ops::Range, str::FromStr};
+
+use serde::Deserialize;
+
+/// Width of a pile column in pixels.
+const COLUMN: f32 = 4.0;
+/// Fraction of its size a landed particle adds to the pile, since flat
+/// pieces of confetti overlap.
+pub const STACKING: f32 = 0.5;
+/// Most columns a landing particle slides down the side of the pile.
+const SLIDE_LIMIT: usize = 32;
+
+/// How the pile disappears once `pile_timeout` has passed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PileClear {
+    /// All particles of the pile fade out together.
+    #[default]
+    Fade,
+    /// A broom runs along the bottom edge and pushes the particles off the
+    /// right side of the screen.
+    Sweep,
+}
+
+impl PileClear {
+    pub const NAMES: [&'static str; 2] = ["fade", "sweep"];
+}
+
+impl FromStr for PileClear {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fade" => Ok(Self::Fade),
+            "sweep" => Ok(Self::Sweep),
+            _ => Err(format!(
+                "Unknown pile clear mode: {} (available: {})",
+                s,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Particles that landed on the bottom edge, kept as a height per column.
+pub struct Pile {
+    /// Height of every column above the bottom edge in pixels.
+    heights: Vec<f32>,
+    /// Tick the first particle of the current pile landed on.
+    pub started: Option<u32>,
+    /// Position of the broom in pixels from the left edge while the pile is
+    /// swept away.
+    pub broom: Option<f32>,
+}
+
+impl Pile {
+    /// Creates an empty pile along a bottom edge `width` pixels long.
+    pub fn new(width: f32) -> Self {
+        Self {
+            heights: vec![0.0; (width / COLUMN) as usize + 1],
+            started: None,
+            broom: None,
+        }
+    }
+
+    /// Height of the pile under a particle `extent` pixels wide at `x`,
+    /// averaged so a particle doesn't get caught on a single peak.
+    pub fn height(&self, x: f32, extent: f32) -> f32 {
+        let columns = &self.heights[self.columns(x, extent)];
+        columns.iter().sum::<f32>() / columns.len() as f32
+    }
+
+    /// Adds a particle `extent` pixels wide that lands at `x`. It first
+    /// slides sideways while the pile next to it is lower by more than half
+    /// its size, so the pile doesn't grow into towers. Returns the position the
+    /// particle settled at and the height it rests on.
+    pub fn land(&mut self, x: f32, extent: f32) -> (f32, f32) {
+        let limit = (self.heights.len() - 1) as f32 * COLUMN;
+        let mut x = x.clamp(0.0, limit);
+        for _ in 0..SLIDE_LIMIT {
+            let here = self.height(x, extent);
+            let left = self.height(x - extent, extent);
+            let right = self.height(x + extent, extent);
+            if left.min(right) + extent * 0.5 >= here {
+                break;
+            }
+            let step = if left < right { -COLUMN } else { COLUMN };
+            x = (x + step).clamp(0.0, limit);
+        }
+
+        let rest = self.height(x, extent);
+        for column in self.columns(x, extent) {
+            self.heights[column] = rest + extent * STACKING;
+        }
+        (x, rest)
+    }
+
+    /// Flattens the pile left of `x`.
+    pub fn clear_until(&mut self, x: f32) {
+        let end = ((x / COLUMN).max(0.0) as usize).min(self.heights.len());
+        self.heights[..end].fill(0.0);
+    }
+
+    pub fn clear(&mut self) {
+        self.heights.fill(0.0);
+    }
+
+    /// Columns covered by a particle `extent` pixels wide at `x`.
+    fn columns(&self, x: f32, extent: f32) -> Range<usize> {
+        let last = self.heights.len() - 1;
+        let start = (((x - extent * 0.5) / COLUMN).max(0.0) as usize).min(last);
+        let end = (((x + extent * 0.5) / COLUMN).max(0.0) as usize).min(last);
+        start..end + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stacks_landed_particles() {
+        let mut pile = Pile::new(100.0);
+        assert_eq!(pile.land(50.0, 8.0), (50.0, 0.0));
+        assert_eq!(pile.height(50.0, 8.0), 8.0 * STACKING);
+        assert_eq!(pile.land(50.0, 8.0), (50.0, 8.0 * STACKING));
+    }
+
+    #[test]
+    fn slides_off_steep_piles() {
+        let mut pile = Pile::new(100.0);
+        for _ in 0..2 {
+            pile.land(50.0, 8.0);
+        }
+        let (x, rest) = pile.land(50.0, 8.0);
+        assert_ne!(x, 50.0);
+        assert!(rest < 2.0 * 8.0 * STACKING, "rests at {}", rest);
+    }
+
+    #[test]
+    fn keeps_particles_on_screen() {
+        let mut pile = Pile::new(100.0);
+        assert_eq!(pile.land(-20.0, 8.0).0, 0.0);
+        assert_eq!(pile.land(500.0, 8.0).0, 100.0);
+    }
+
+    #[test]
+    fn clears_left_of_the_broom() {
+        let mut pile = Pile::new(100.0);
+        pile.land(10.0, 8.0);
+        pile.land(90.0, 8.0);
+        pile.clear_until(50.0);
+        assert_eq!(pile.height(10.0, 8.0), 0.0);
+        assert_eq!(pile.height(90.0, 8.0), 8.0 * STACKING);
+        pile.clear();
+        assert_eq!(pile.height(90.0, 8.0), 0.0);
+    }
+}
@@ -0,0 +1,133 @@
+use std::str::FromStr;
+
+use rand::Rng;
+use serde::Deserialize;
+
+/// A range particles pick a random value from, written as `MIN..MAX` or as a
+/// single number for a fixed value.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "RangeEntry")]
+pub struct RandomRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl RandomRange {
+    pub const fn new(min: f32, max: f32) -> Self {
+        Self { min, max }
+    }
+
+    pub const fn fixed(value: f32) -> Self {
+        Self::new(value, value)
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> f32 {
+        if self.min < self.max {
+            rng.random_range(self.min..=self.max)
+        } else {
+            self.min
+        }
+    }
+
+    fn checked(min: f32, max: f32) -> Result<Self, String> {
+        if !min.is_finite() || !max.is_finite() {
+            return Err("range bounds must be finite".to_string());
+        }
+        if min > max {
+            return Err(format!("range {}..{} is reversed", min, max));
+        }
+        Ok(Self::new(min, max))
+    }
+}
+
+impl FromStr for RandomRange {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |v: &str| {
+            v.trim()
+                .parse::<f32>()
+                .map_err(|_| "expected MIN..MAX or a number".to_string())
+        };
+        match s.split_once("..") {
+            Some((min, max)) => Self::checked(parse(min)?, parse(max)?),
+            None => {
+                let value = parse(s)?;
+                Self::checked(value, value)
+            }
+        }
+    }
+}
+
+/// A range in the config file: a number, `[min, max]` or a string as accepted
+/// by `RandomRange::from_str`.
+#[derive(Deserialize)]
+#[serde(
+    untagged,
+    expecting = "expected a number, [min, max] or a string like \"0.5..1.5\""
+)]
+enum RangeEntry {
+    Fixed(f32),
+    Bounds([f32; 2]),
+    Spec(String),
+}
+
+impl TryFrom<RangeEntry> for RandomRange {
+    type Error = String;
+    fn try_from(entry: RangeEntry) -> Result<Self, Self::Error> {
+        match entry {
+            RangeEntry::Fixed(value) => Self::checked(value, value),
+            RangeEntry::Bounds([min, max]) => Self::checked(min, max),
+            RangeEntry::Spec(spec) => spec.parse(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!("0.5..1.5".parse(), Ok(RandomRange::new(0.5, 1.5)));
+        assert_eq!(" -1 .. -0.5 ".parse(), Ok(RandomRange::new(-1.0, -0.5)));
+        assert_eq!("2".parse(), Ok(RandomRange::fixed(2.0)));
+        assert_eq!("1..1".parse(), Ok(RandomRange::fixed(1.0)));
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        let error = |s: &str| s.parse::<RandomRange>().unwrap_err();
+        assert_eq!(error("fast"), "expected MIN..MAX or a number");
+        assert_eq!(error("1.."), "expected MIN..MAX or a number");
+        assert_eq!(error("2..1"), "range 2..1 is reversed");
+        assert_eq!(error("0..inf"), "range bounds must be finite");
+    }
+
+    #[test]
+    fn converts_config_entries() {
+        let range = |entry| RandomRange::try_from(entry);
+        assert_eq!(range(RangeEntry::Fixed(2.0)), Ok(RandomRange::fixed(2.0)));
+        assert_eq!(
+            range(RangeEntry::Bounds([1.0, 3.0])),
+            Ok(RandomRange::new(1.0, 3.0))
+        );
+        assert_eq!(
+            range(RangeEntry::Spec("0.5..1".to_string())),
+            Ok(RandomRange::new(0.5, 1.0))
+        );
+        assert_eq!(
+            range(RangeEntry::Bounds([3.0, 1.0])),
+            Err("range 3..1 is reversed".to_string())
+        );
+    }
+
+    #[test]
+    fn samples_within_bounds() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let range = RandomRange::new(0.5, 1.5);
+        assert!((0..100).all(|_| (0.5..=1.5).contains(&range.sample(&mut rng))));
+        assert_eq!(RandomRange::fixed(2.0).sample(&mut rng), 2.0);
+    }
+}
@@ -1,40 +1,266 @@
 struct Uniforms {
     time: f32,
+    // Surface size in logical pixels
+    resolution: vec2<f32>,
 };
 
+// Particles are simulated in `simulation.rs`, the shader only places and shades them
 struct VertexInput {
     @location(0) position: vec2<f32>,
-    @location(1) v_start: vec2<f32>,
+    @location(1) center: vec2<f32>,
     @location(2) color: vec3<f32>,  // Add color if you want per-vertex or per-instance colors
+    @location(3) alpha: f32,
+    @location(4) seed: u32,
+    @location(5) shape: u32,
+    @location(6) axis: vec3<f32>,
+    @location(7) tumble: f32,
+    @location(8) size: f32,
+    @location(9) rotation: f32,
 };
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
-    @location(0) color: vec3<f32>,  // Pass color to fragment shader
+    @location(0) color: vec3<f32>,  // Linear color, passed to fragment shader
+    @location(1) alpha: f32,
+    // Shape id and position on the quad from -1 to 1, for `shape_distance`
+    @location(2) @interpolate(flat, either) shape: u32,
+    @location(3) local: vec2<f32>,
 };
 
 @group(0) @binding(0)
 var<uniform> uniforms: Uniforms;
 
-var<private> g: vec2<f32> = vec2<f32>(0.0, -0.2);
+// Images for textured particles, see `create_atlas`
+@group(1) @binding(0)
+var atlas_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var atlas_sampler: sampler;
+
+// Edge length of an atlas cell in texels, `atlas::CELL_SIZE`
+const ATLAS_CELL: f32 = 64.0;
+
+// Set from the surface configuration in `create_pipeline`
+override SRGB_SURFACE: bool = true;
+override PREMULTIPLIED: bool = true;
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+// Same conversions as `color::srgb_to_oklab` and `color::oklab_to_srgb`
+fn srgb_to_oklab(srgb: vec3<f32>) -> vec3<f32> {
+    let c = srgb_to_linear(srgb);
+    let lms = vec3<f32>(
+        0.41222146 * c.r + 0.53633255 * c.g + 0.051445995 * c.b,
+        0.2119035 * c.r + 0.6806995 * c.g + 0.10739696 * c.b,
+        0.08830246 * c.r + 0.28171885 * c.g + 0.6299787 * c.b,
+    );
+    let l = pow(lms, vec3<f32>(1.0 / 3.0));
+    return vec3<f32>(
+        0.21045426 * l.x + 0.7936178 * l.y - 0.004072047 * l.z,
+        1.9779985 * l.x - 2.4285922 * l.y + 0.4505937 * l.z,
+        0.025904037 * l.x + 0.78277177 * l.y - 0.80867577 * l.z,
+    );
+}
+
+// Returns linear light, the encoding is left to `fs_main`
+fn oklab_to_linear(lab: vec3<f32>) -> vec3<f32> {
+    let l = lab.x + 0.39633778 * lab.y + 0.21580376 * lab.z;
+    let m = lab.x - 0.105561346 * lab.y - 0.06385417 * lab.z;
+    let s = lab.x - 0.08948418 * lab.y - 1.2914855 * lab.z;
+    let lms = vec3<f32>(l * l * l, m * m * m, s * s * s);
+    let c = vec3<f32>(
+        4.0767417 * lms.x - 3.3077116 * lms.y + 0.23096994 * lms.z,
+        -1.268438 * lms.x + 2.6097574 * lms.y - 0.34131938 * lms.z,
+        -0.0041960863 * lms.x - 0.7034186 * lms.y + 1.7076147 * lms.z,
+    );
+    return clamp(c, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// Converts logical pixels from the top left corner to clip space
+fn to_clip(pixels: vec2<f32>) -> vec4<f32> {
+    let ndc = pixels / uniforms.resolution * 2.0 - 1.0;
+    return vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+}
+
+fn hash(n: u32) -> f32 {
+    return fract(sin(f32(n) * 12.9898) * 43758.5453);
+}
+
+// Shape ids, see `Shape` in shape.rs
+const SHAPE_SQUARE: u32 = 0u;
+const SHAPE_CIRCLE: u32 = 1u;
+const SHAPE_TRIANGLE: u32 = 2u;
+const SHAPE_STAR: u32 = 3u;
+const SHAPE_HEART: u32 = 4u;
+// Flags of atlas cells, `shape::TEXTURED` and `shape::TINTED`
+const TEXTURED: u32 = 0x80000000u;
+const TINTED: u32 = 0x40000000u;
+
+// Signed distance functions after https://iquilezles.org/articles/distfunctions2d/
+fn sd_triangle(q: vec2<f32>, r: f32) -> f32 {
+    let k = sqrt(3.0);
+    var p = vec2<f32>(abs(q.x) - r, q.y + r / k);
+    if p.x + k * p.y > 0.0 {
+        p = vec2<f32>(p.x - k * p.y, -k * p.x - p.y) / 2.0;
+    }
+    p.x -= clamp(p.x, -2.0 * r, 0.0);
+    return -length(p) * sign(p.y);
+}
+
+fn sd_star(q: vec2<f32>, r: f32, inner: f32) -> f32 {
+    // Normals of the mirror lines between the star's arms
+    let k1 = vec2<f32>(0.809017, -0.587785);
+    let k2 = vec2<f32>(-0.809017, -0.587785);
+    var p = vec2<f32>(abs(q.x), q.y);
+    p -= 2.0 * max(dot(k1, p), 0.0) * k1;
+    p -= 2.0 * max(dot(k2, p), 0.0) * k2;
+    p.x = abs(p.x);
+    p.y -= r;
+    let ba = inner * vec2<f32>(0.587785, 0.809017) - vec2<f32>(0.0, 1.0);
+    let h = clamp(dot(p, ba) / dot(ba, ba), 0.0, r);
+    return length(p - ba * h) * sign(p.y * ba.x - p.x * ba.y);
+}
+
+fn sd_heart(q: vec2<f32>) -> f32 {
+    let p = vec2<f32>(abs(q.x), q.y);
+    if p.x + p.y > 1.0 {
+        return length(p - vec2<f32>(0.25, 0.75)) - sqrt(2.0) / 4.0;
+    }
+    let a = p - vec2<f32>(0.0, 1.0);
+    let b = p - 0.5 * max(p.x + p.y, 0.0);
+    return sqrt(min(dot(a, a), dot(b, b))) * sign(p.x - p.y);
+}
+
+// Distance to the outline in quad units, negative inside
+fn shape_distance(shape: u32, p: vec2<f32>) -> f32 {
+    switch shape {
+        case SHAPE_CIRCLE: {
+            return length(p) - 1.0;
+        }
+        case SHAPE_TRIANGLE: {
+            // Moved down so the centroid is not above the quad's center
+            return sd_triangle(p + vec2<f32>(0.0, 0.2887), 1.0);
+        }
+        case SHAPE_STAR: {
+            return sd_star(p, 1.0, 0.45);
+        }
+        case SHAPE_HEART: {
+            return sd_heart(p * 0.6 + vec2<f32>(0.0, 0.5)) / 0.6;
+        }
+        default: {
+            // The whole quad
+            return -1.0;
+        }
+    }
+}
+
+// Samples the atlas cell of a textured shape
+fn sample_atlas(shape: u32, p: vec2<f32>) -> vec4<f32> {
+    let size = vec2<f32>(textureDimensions(atlas_texture));
+    let columns = max(u32(size.x / ATLAS_CELL), 1u);
+    let cell = shape & 0xffffu;
+    let corner = vec2<f32>(f32(cell % columns), f32(cell / columns)) * ATLAS_CELL;
+    // Texture rows run top to bottom, the quad's y points up
+    let local = vec2<f32>(p.x, -p.y) * 0.5 + 0.5;
+    let texel = corner + local * ATLAS_CELL;
+    // The mip level is picked here, so the filter can be kept half a texel
+    // of the coarser level inside the cell and never reaches a neighbour
+    let footprint = max(length(dpdx(texel)), length(dpdy(texel)));
+    let lod = clamp(log2(footprint), 0.0, f32(textureNumLevels(atlas_texture) - 1u));
+    let inset = 0.5 * exp2(ceil(lod));
+    let inside = clamp(texel, corner + inset, corner + ATLAS_CELL - inset);
+    return textureSampleLevel(atlas_texture, atlas_sampler, inside / size, lod);
+}
+
+// Rotates `v` by `angle` around the unit vector `axis` (Rodrigues' formula)
+fn rotate(v: vec3<f32>, axis: vec3<f32>, angle: f32) -> vec3<f32> {
+    let c = cos(angle);
+    let s = sin(angle);
+    return v * c + cross(axis, v) * s + axis * dot(axis, v) * (1.0 - c);
+}
+
+// Light falls in from the top left, the viewer looks along -z
+const LIGHT: vec3<f32> = vec3<f32>(-0.32, 0.49, 0.81);
+const HALFWAY: vec3<f32> = vec3<f32>(-0.17, 0.26, 0.95);
 
 @vertex
 fn vs_main(input: VertexInput) -> VertexOutput {
-    let t = uniforms.time;
-    let decay = 0.01;
+    // The quad is turned in its plane, then tumbles like a piece of paper
+    // and is projected orthographically
+    let turn = vec2<f32>(cos(input.rotation), sin(input.rotation));
+    let flat = vec2<f32>(
+        input.position.x * turn.x - input.position.y * turn.y,
+        input.position.x * turn.y + input.position.y * turn.x,
+    ) * input.size;
+    let angle = input.tumble + hash(input.seed) * 6.2831;
+    let corner = rotate(vec3<f32>(flat, 0.0), input.axis, angle);
+    let normal = rotate(vec3<f32>(0.0, 0.0, 1.0), input.axis, angle);
 
-    let new_x = clamp(input.position.x + input.v_start.x * t - t * decay, -1.0, 1.0);
-    let new_y = input.position.y + input.v_start.y * t - t * t;
+    // Both sides are lit, the back side is a little darker, and a face
+    // turned towards the light flashes briefly
+    let diffuse = abs(dot(normal, LIGHT));
+    let back = select(0.0, 0.08, normal.z < 0.0);
+    let specular = pow(abs(dot(normal, HALFWAY)), 48.0) * 0.35;
+    let lab = srgb_to_oklab(input.color);
+    let lightness = lab.x * (0.75 + 0.25 * diffuse) - back;
 
     var output: VertexOutput;
-    output.position = vec4<f32>(new_x, new_y, 0.0, 1.0);
-    output.color = input.color;
+    // The quad's y points up, screen pixels count down from the top
+    output.position = to_clip(input.center + vec2<f32>(corner.x, -corner.y));
+    output.color = oklab_to_linear(vec3<f32>(lightness, lab.yz)) + specular;
+    output.alpha = input.alpha;
+    output.shape = input.shape;
+    output.local = sign(input.position);
+
+    return output;
+}
+
+struct RibbonInput {
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec3<f32>,
+    @location(2) alpha: f32,
+    // Lightness offset for the back side
+    @location(3) shade: f32,
+};
+
+@vertex
+fn vs_ribbon(input: RibbonInput) -> VertexOutput {
+    let lab = srgb_to_oklab(input.color);
+
+    var output: VertexOutput;
+    output.position = to_clip(input.position);
+    output.color = oklab_to_linear(vec3<f32>(lab.x + input.shade, lab.yz));
+    output.alpha = input.alpha;
+    output.shape = SHAPE_SQUARE;
+    output.local = vec2<f32>(0.0);
 
     return output;
 }
 
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(input.color, 1.0);
+    // Antialiased over about one pixel, so shapes stay crisp at any size
+    let distance = shape_distance(input.shape, input.local);
+    var coverage = clamp(0.5 - distance / max(fwidth(distance), 1e-4), 0.0, 1.0);
+    var linear = input.color;
+    // Sampled for every fragment, derivatives need uniform control flow
+    let texel = sample_atlas(input.shape, input.local);
+    if (input.shape & TEXTURED) != 0u {
+        // The atlas is premultiplied so filtering doesn't darken the edges
+        let rgb = texel.rgb / max(texel.a, 1e-4);
+        coverage = texel.a;
+        linear = select(rgb, input.color * rgb, (input.shape & TINTED) != 0u);
+    }
+    let alpha = input.alpha * coverage;
+    // sRGB surfaces encode on write, others get the encoded values directly
+    let color = select(linear_to_srgb(linear), linear, SRGB_SURFACE);
+    if PREMULTIPLIED {
+        return vec4<f32>(color * alpha, alpha);
+    }
+    return vec4<f32>(color, alpha);
 }
-
@@ -0,0 +1,65 @@
+use std::str::FromStr;
+
+use crate::weighted;
+
+/// Set on shape ids that refer to a cell of the texture atlas, the lower 16
+/// bits hold the cell index.
+pub const TEXTURED: u32 = 1 << 31;
+/// Set on textured shape ids whose texels are multiplied by the particle
+/// color.
+pub const TINTED: u32 = 1 << 30;
+
+/// Outline of a particle, cut out of its quad in the fragment shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Square,
+    Circle,
+    Triangle,
+    Star,
+    Heart,
+}
+
+impl Shape {
+    pub const NAMES: [&'static str; 5] = ["square", "circle", "triangle", "star", "heart"];
+
+    /// Id passed to the shader in `InstanceData`, see `shape_distance` in
+    /// `shader.wgsl`.
+    pub fn id(self) -> u32 {
+        self as u32
+    }
+}
+
+impl FromStr for Shape {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "square" | "rectangle" => Ok(Self::Square),
+            "circle" => Ok(Self::Circle),
+            "triangle" => Ok(Self::Triangle),
+            "star" => Ok(Self::Star),
+            "heart" => Ok(Self::Heart),
+            _ => Err(format!(
+                "Unknown shape: {} (available: {})",
+                s.trim(),
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Parses a comma separated list of shapes, each with an optional `:weight`
+/// suffix.
+pub fn parse_shape_list(list: &str) -> Result<Vec<(Shape, u32)>, String> {
+    let mut shapes = Vec::new();
+    for token in list.split(',') {
+        if token.trim().is_empty() {
+            continue;
+        }
+        let (shape, weight) = weighted::split_weight(token)?;
+        shapes.push((shape.parse::<Shape>()?, weight));
+    }
+    if shapes.is_empty() {
+        return Err(format!("No shapes in '{}'", list));
+    }
+    Ok(shapes)
+}
@@ -0,0 +1,162 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    color,
+    color_palette::ColorVec,
+    config::{config_home, expand_home},
+};
+
+/// Variable names checked for each role, most specific first.
+const ACCENT_NAMES: [&str; 6] = [
+    "accent",
+    "accent-color",
+    "accent-bg-color",
+    "theme-selected-bg-color",
+    "primary",
+    "highlight",
+];
+const BACKGROUND_NAMES: [&str; 6] = [
+    "background",
+    "background-color",
+    "bg",
+    "bg-color",
+    "theme-bg-color",
+    "window-bg-color",
+];
+const TEXT_NAMES: [&str; 7] = [
+    "text",
+    "text-color",
+    "foreground",
+    "fg",
+    "fg-color",
+    "theme-fg-color",
+    "window-fg-color",
+];
+
+/// Hue offsets in degrees around the accent color.
+const HUE_OFFSETS: [f32; 6] = [0.0, -35.0, 35.0, 150.0, 180.0, 210.0];
+
+/// Builds a palette from the colors of the Sherlock launcher theme. Returns
+/// `None` if no stylesheet or no usable colors were found.
+pub fn palette() -> Option<ColorVec> {
+    let config_dir = config_home()?.join("sherlock");
+    let css = fs::read_to_string(stylesheet_path(&config_dir)).ok()?;
+    let variables = css_variables(&css);
+    let lookup = |names: &[&str]| names.iter().find_map(|name| variables.get(*name).copied());
+
+    let accent = lookup(&ACCENT_NAMES);
+    let background = lookup(&BACKGROUND_NAMES);
+    let text = lookup(&TEXT_NAMES);
+    Some(harmonize(
+        accent.or(text).or(background)?,
+        background,
+        accent,
+    ))
+}
+
+/// Derives evenly bright colors at harmonious hues around `base`. The
+/// lightness is chosen to stand out against the background.
+fn harmonize(base: [f32; 3], background: Option<[f32; 3]>, accent: Option<[f32; 3]>) -> ColorVec {
+    let [_, chroma, hue] = color::oklab_to_oklch(color::srgb_to_oklab(base));
+    let dark_background = background
+        .map(|bg| color::srgb_to_oklab(bg)[0] < 0.6)
+        .unwrap_or(true);
+    let lightness = if dark_background { 0.78 } else { 0.6 };
+    // Greyish themes still get colorful confetti
+    let chroma = chroma.max(0.12);
+
+    let mut colors = accent.into_iter().collect::<ColorVec>();
+    colors.extend(
+        HUE_OFFSETS
+            .iter()
+            .skip(colors.len())
+            .map(|offset| color::oklch_to_srgb([lightness, chroma, hue + offset])),
+    );
+    colors
+}
+
+/// Reads the stylesheet location from the `[files]` section of Sherlock's
+/// config, falling back to `main.css` in the config directory.
+fn stylesheet_path(config_dir: &Path) -> PathBuf {
+    let from_toml = || {
+        let content = fs::read_to_string(config_dir.join("config.toml")).ok()?;
+        let config = toml::from_str::<toml::Table>(&content).ok()?;
+        Some(config.get("files")?.get("css")?.as_str()?.to_string())
+    };
+    let from_json = || {
+        let content = fs::read_to_string(config_dir.join("config.json")).ok()?;
+        let config = serde_json::from_str::<serde_json::Value>(&content).ok()?;
+        Some(config.pointer("/files/css")?.as_str()?.to_string())
+    };
+
+    match from_toml().or_else(from_json) {
+        Some(path) => expand_home(Path::new(&path)),
+        None => config_dir.join("main.css"),
+    }
+}
+
+/// Collects the color variables of a stylesheet, both GTK's
+/// `@define-color name value;` and custom properties `--name: value;`.
+/// Names are normalized to lowercase with dashes.
+fn css_variables(css: &str) -> HashMap<String, [f32; 3]> {
+    let css = strip_comments(css);
+    let mut raw = HashMap::new();
+    for statement in css.split([';', '{', '}']) {
+        let statement = statement.trim();
+        let entry = if let Some(rest) = statement.strip_prefix("@define-color") {
+            rest.trim().split_once(char::is_whitespace)
+        } else if let Some(rest) = statement.strip_prefix("--") {
+            rest.split_once(':')
+        } else {
+            None
+        };
+        if let Some((name, value)) = entry {
+            raw.insert(normalize(name), value.trim().to_string());
+        }
+    }
+
+    raw.keys()
+        .filter_map(|name| Some((name.clone(), resolve(&raw, name)?)))
+        .collect()
+}
+
+/// Resolves a variable, following `@name` and `var(--name)` references.
+fn resolve(raw: &HashMap<String, String>, name: &str) -> Option<[f32; 3]> {
+    let mut value = raw.get(name)?;
+    // Bounded to guard against reference cycles
+    for _ in 0..8 {
+        let reference = value.strip_prefix('@').or_else(|| {
+            value
+                .strip_prefix("var(")?
+                .strip_suffix(')')?
+                .trim()
+                .strip_prefix("--")
+        });
+        match reference {
+            Some(reference) => value = raw.get(&normalize(reference))?,
+            None => return color::parse_color(value).ok(),
+        }
+    }
+    None
+}
+
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase().replace('_', "-")
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut result = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        result.push_str(&rest[..start]);
+        rest = rest[start + 2..]
+            .find("*/")
+            .map_or("", |end| &rest[start + 2 + end + 2..]);
+    }
+    result.push_str(rest);
+    result
+}
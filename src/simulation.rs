@@ -0,0 +1,743 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{
+    InstanceData, PARTICLE_SIZE, RibbonVertex, color,
+    config::Config,
+    edge::EdgeMode,
+    emitter::{Emitter, Payload},
+    pile::{Pile, PileClear, STACKING},
+    shape::Shape,
+    weighted::Weighted,
+    wind::Wind,
+};
+
+/// The simulation advances in fixed steps, like canvas-confetti's animation
+/// frames.
+pub const TICK_RATE: f32 = 60.0;
+/// Downward acceleration of rising rockets in pixels per tick².
+const ROCKET_GRAVITY: f32 = 0.45;
+/// Lifetime of the sparks a rocket leaves behind, in ticks.
+const TRAIL_TICKS: u32 = 20;
+/// Distance beyond the edges of the screen in pixels where particles spawn,
+/// wrap around or are removed, so they don't pop in and out of view.
+const EDGE_MARGIN: f32 = 20.0;
+/// Ticks a cleared pile takes to fade out.
+const PILE_FADE_TICKS: u32 = 60;
+/// Pixels per tick the broom moves while sweeping the pile away.
+const BROOM_SPEED: f32 = 12.0;
+
+/// Points in the chain of a ribbon.
+const RIBBON_POINTS: usize = 12;
+/// Vertices of a ribbon's triangle strip, two per point.
+pub const RIBBON_VERTICES: usize = RIBBON_POINTS * 2;
+/// Distance between two ribbon points in pixels.
+const RIBBON_SEGMENT: f32 = 9.0;
+/// Ribbon width in pixels at a `size` of 1.
+const RIBBON_WIDTH: f32 = 7.0;
+/// Fraction of its speed a ribbon point keeps from one tick to the next.
+const RIBBON_DAMPING: f32 = 0.9;
+/// Passes over the distance constraints per tick.
+const RIBBON_ITERATIONS: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Confetti,
+    /// Rises until it comes to a halt, then bursts into `sparks` particles.
+    Rocket {
+        sparks: u32,
+        speed: f32,
+    },
+    Spark,
+    /// Falls at a constant speed while swaying from side to side.
+    Falling {
+        sway: f32,
+        /// Angular speed of the sway in radians per tick.
+        frequency: f32,
+        phase: f32,
+        /// Fall speed in pixels per tick.
+        speed: f32,
+    },
+    /// Lies on the pile with the opacity it had when it landed, and fades
+    /// out once the pile is cleared.
+    Landed {
+        alpha: f32,
+        fading: bool,
+    },
+    /// Pushed along the bottom edge by the broom that sweeps the pile away.
+    Swept {
+        alpha: f32,
+    },
+}
+
+struct Particle {
+    kind: Kind,
+    /// Center in logical pixels from the top left corner.
+    position: [f32; 2],
+    /// Pixels per tick, y pointing down.
+    velocity: [f32; 2],
+    color: [f32; 3],
+    /// Shape id, see `Shape::id` and `shape::TEXTURED`.
+    shape: u32,
+    /// Unit vector the particle tumbles around.
+    axis: [f32; 3],
+    /// Tumbling speed in radians per second.
+    spin: f32,
+    /// Tumbling angle in radians.
+    tumble: f32,
+    size: f32,
+    /// In-plane rotation in radians.
+    rotation: f32,
+    /// In-plane rotation per tick in radians.
+    angular_velocity: f32,
+    opacity: f32,
+    /// Ticks left before the particle starts moving and becomes visible.
+    delay: u32,
+    age: u32,
+    lifetime: u32,
+    seed: u32,
+}
+
+impl Particle {
+    fn alpha(&self) -> f32 {
+        let fade = 1.0 - self.age as f32 / self.lifetime as f32;
+        match self.kind {
+            Kind::Rocket { .. } | Kind::Falling { .. } => self.opacity,
+            Kind::Landed { alpha, fading } => alpha * if fading { fade } else { 1.0 },
+            Kind::Swept { alpha } => alpha,
+            Kind::Confetti | Kind::Spark => fade * self.opacity,
+        }
+    }
+
+    /// Edge length of the particle in pixels.
+    fn extent(&self, scale: f32) -> f32 {
+        PARTICLE_SIZE * scale * self.size
+    }
+}
+
+/// A streamer, simulated as a chain of points with verlet integration. The
+/// head flies like a confetti particle and drags the rest of the chain along.
+struct Ribbon {
+    /// Points in logical pixels, the head first.
+    points: [[f32; 2]; RIBBON_POINTS],
+    /// Positions of the previous tick, which carry the verlet velocity.
+    previous: [[f32; 2]; RIBBON_POINTS],
+    /// Launch velocity of the head in pixels per tick, y pointing down.
+    velocity: [f32; 2],
+    color: [f32; 3],
+    /// Rotation around the long axis per point and per tick, in radians.
+    twist: f32,
+    spin: f32,
+    phase: f32,
+    age: u32,
+    lifetime: u32,
+}
+
+impl Ribbon {
+    /// Moves the ribbon on by a tick, `air` is the wind at its head.
+    fn step(&mut self, config: &Config, [width, height]: [f32; 2], air: [f32; 2]) {
+        self.age += 1;
+
+        let drift = config.drift + air[0];
+        let gravity = 3.0 * config.gravity + air[1];
+        let [vx, vy] = self.velocity;
+        let [x, y] = self.points[0];
+        let moved = [x + vx + drift, y + vy + gravity];
+        self.velocity = [vx * config.decay, vy * config.decay];
+
+        // The whole chain has to be out of view before it wraps or despawns
+        let margin = EDGE_MARGIN + RIBBON_SEGMENT * RIBBON_POINTS as f32;
+        let mut head = moved;
+        let inside = config.edges.sides(
+            &mut head[0],
+            &mut self.velocity[0],
+            drift,
+            width,
+            margin,
+            config.restitution,
+        ) && config.floor.floor(
+            &mut head[1],
+            &mut self.velocity[1],
+            gravity,
+            height,
+            margin,
+            config.restitution,
+        );
+        if !inside {
+            self.lifetime = self.age;
+        }
+        // A wrapping head takes the chain along, other modes only stop it
+        for (axis, mode) in [config.edges, config.floor].into_iter().enumerate() {
+            if mode == EdgeMode::Wrap {
+                let shift = head[axis] - moved[axis];
+                for point in self.points.iter_mut().chain(&mut self.previous) {
+                    point[axis] += shift;
+                }
+            }
+        }
+        self.points[0] = head;
+
+        // Drifts and falls as fast as the head once the chain reaches its top
+        // speed
+        let pull = [drift, gravity].map(|speed| speed * (1.0 - RIBBON_DAMPING));
+        for (point, previous) in self.points.iter_mut().zip(&mut self.previous).skip(1) {
+            let current = *point;
+            point[0] += (current[0] - previous[0]) * RIBBON_DAMPING + pull[0];
+            point[1] += (current[1] - previous[1]) * RIBBON_DAMPING + pull[1];
+            *previous = current;
+        }
+
+        let segment = RIBBON_SEGMENT;
+        for _ in 0..RIBBON_ITERATIONS {
+            for i in 0..RIBBON_POINTS - 1 {
+                let [a, b] = [self.points[i], self.points[i + 1]];
+                let delta = [b[0] - a[0], b[1] - a[1]];
+                let length = (delta[0] * delta[0] + delta[1] * delta[1]).sqrt();
+                if length <= f32::EPSILON {
+                    continue;
+                }
+                // Only the tail side moves when pulling on the head
+                let weight = if i == 0 { 1.0 } else { 0.5 };
+                let correction = (length - segment) / length * weight;
+                self.points[i + 1][0] -= delta[0] * correction;
+                self.points[i + 1][1] -= delta[1] * correction;
+                if i > 0 {
+                    self.points[i][0] += delta[0] * correction;
+                    self.points[i][1] += delta[1] * correction;
+                }
+            }
+        }
+    }
+
+    /// Builds the triangle strip. The width follows the cosine of the twist
+    /// angle, and the back side is shaded darker.
+    fn vertices(&self, size: f32) -> impl Iterator<Item = RibbonVertex> + '_ {
+        let alpha = 1.0 - self.age as f32 / self.lifetime as f32;
+        (0..RIBBON_POINTS).flat_map(move |i| {
+            let a = self.points[i.saturating_sub(1)];
+            let b = self.points[(i + 1).min(RIBBON_POINTS - 1)];
+            let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+            let length = (dx * dx + dy * dy).sqrt();
+            let normal = if length > f32::EPSILON {
+                [-dy / length, dx / length]
+            } else {
+                [1.0, 0.0]
+            };
+
+            let facing = (self.phase + self.spin * self.age as f32 + self.twist * i as f32).cos();
+            let half_width = RIBBON_WIDTH * size * 0.5 * facing.abs().max(0.15);
+            let shade = if facing < 0.0 { -0.12 } else { 0.0 };
+            let point = self.points[i];
+            [1.0, -1.0].map(|side| RibbonVertex {
+                position: [
+                    point[0] + normal[0] * half_width * side,
+                    point[1] + normal[1] * half_width * side,
+                ],
+                color: self.color,
+                alpha,
+                shade,
+            })
+        })
+    }
+}
+
+/// Particle state, stepped on the CPU and uploaded as instances every frame.
+pub struct Simulation {
+    config: Config,
+    colors: Weighted<[f32; 3]>,
+    /// Shape ids particles are picked from.
+    shapes: Weighted<u32>,
+    particles: Vec<Particle>,
+    ribbons: Vec<Ribbon>,
+    pile: Pile,
+    wind: Wind,
+    /// Emitters that have not fired yet, the next one last.
+    pending: Vec<Emitter>,
+    /// Fired stream emitters and the fraction of a particle they have
+    /// accumulated towards the next spawn.
+    streams: Vec<(Emitter, f32)>,
+    /// Surface size in logical pixels.
+    resolution: [f32; 2],
+    tick: u32,
+    rng: StdRng,
+}
+
+impl Simulation {
+    /// Creates the simulation, `textured` holds the shape ids of the atlas
+    /// cells with their weights.
+    pub fn new(config: &Config, textured: &[(u32, u32)]) -> Self {
+        let mut pending = config.preset.emitters(config);
+        pending.sort_by(|a, b| b.delay.total_cmp(&a.delay));
+        let mut shapes: Vec<(u32, u32)> = config
+            .shapes
+            .iter()
+            .map(|(shape, weight)| (shape.id(), *weight))
+            .chain(textured.iter().copied())
+            .collect();
+        if shapes.is_empty() {
+            shapes.push((Shape::Square.id(), 1));
+        }
+        let mut rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        Self {
+            config: config.clone(),
+            colors: Weighted::new(config.palette.weighted_colors(&mut rng))
+                .expect("palettes are never empty"),
+            shapes: Weighted::new(shapes).expect("there is at least one shape"),
+            particles: Vec::new(),
+            ribbons: Vec::new(),
+            pile: Pile::new(1.0),
+            pending,
+            streams: Vec::new(),
+            resolution: [1.0, 1.0],
+            tick: 0,
+            wind: Wind::new(config, rng.random()),
+            rng,
+        }
+    }
+
+    /// Resizes the screen. The pile is rebuilt bottom up on the new floor, so
+    /// its particles stay on top of it.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let [_, old_height] = self.resolution;
+        let [width, height] = [width as f32, height as f32];
+        self.resolution = [width, height];
+
+        let mut pile = Pile::new(width);
+        pile.started = self.pile.started;
+        pile.broom = self.pile.broom;
+        let mut landed = Vec::new();
+        for particle in &mut self.particles {
+            match particle.kind {
+                // Already cleared off the pile, they only follow the floor
+                Kind::Landed { fading: true, .. } => particle.position[1] += height - old_height,
+                Kind::Landed { fading: false, .. } => landed.push(particle),
+                _ => {}
+            }
+        }
+        landed.sort_by(|a, b| b.position[1].total_cmp(&a.position[1]));
+        for particle in landed {
+            let extent = particle.extent(self.config.size);
+            let (x, rest) = pile.land(particle.position[0], extent);
+            particle.position = [x, height - rest - extent * STACKING];
+        }
+        self.pile = pile;
+    }
+
+    /// Steps the simulation up to `time` seconds after the start.
+    pub fn advance(&mut self, time: f32) {
+        let target = (time * TICK_RATE) as u32;
+        while self.tick < target {
+            self.step();
+            self.tick += 1;
+        }
+    }
+
+    /// True once every emitter has fired and all particles are gone. Streams
+    /// never finish.
+    pub fn is_finished(&self) -> bool {
+        self.pending.is_empty()
+            && self.streams.is_empty()
+            && self.particles.is_empty()
+            && self.ribbons.is_empty()
+    }
+
+    pub fn instances(&self) -> Vec<InstanceData> {
+        self.particles
+            .iter()
+            .filter(|particle| particle.delay == 0)
+            .map(|particle| InstanceData {
+                position: particle.position,
+                color: particle.color,
+                alpha: particle.alpha(),
+                seed: particle.seed,
+                shape: particle.shape,
+                axis: particle.axis,
+                tumble: particle.tumble,
+                size: particle.size,
+                rotation: particle.rotation,
+            })
+            .collect()
+    }
+
+    /// Triangle strips of all ribbons, `RIBBON_VERTICES` per ribbon.
+    pub fn ribbon_vertices(&self) -> Vec<RibbonVertex> {
+        self.ribbons
+            .iter()
+            .flat_map(|ribbon| ribbon.vertices(self.config.size))
+            .collect()
+    }
+
+    fn step(&mut self) {
+        while let Some(emitter) = self
+            .pending
+            .pop_if(|e| e.delay * TICK_RATE <= self.tick as f32)
+        {
+            self.fire(&emitter);
+        }
+        self.spawn_streams();
+        self.wind.update(self.tick as f32 / TICK_RATE);
+
+        let [width, height] = self.resolution;
+        let mut trails = Vec::new();
+        let mut explosions = Vec::new();
+        for particle in &mut self.particles {
+            if particle.delay > 0 {
+                particle.delay -= 1;
+                continue;
+            }
+            particle.age += 1;
+            particle.rotation += particle.angular_velocity;
+            particle.tumble += particle.spin / TICK_RATE;
+            match particle.kind {
+                Kind::Rocket { sparks, speed } => {
+                    particle.position[0] += particle.velocity[0];
+                    particle.position[1] += particle.velocity[1];
+                    particle.velocity[1] += ROCKET_GRAVITY;
+                    if particle.velocity[1] >= 0.0 {
+                        particle.lifetime = particle.age;
+                        explosions.push((particle.position, particle.color, sparks, speed));
+                    } else {
+                        trails.push((particle.position, particle.color));
+                    }
+                }
+                Kind::Confetti | Kind::Spark | Kind::Falling { .. } => {
+                    // canvas-confetti moves gravity and drift at a constant
+                    // speed and only decays the launch velocity
+                    let config = &self.config;
+                    let air = self.wind.at(particle.position);
+                    let (sway, fall) = match particle.kind {
+                        Kind::Falling {
+                            sway,
+                            frequency,
+                            phase,
+                            speed,
+                        } => (
+                            sway * (phase + particle.age as f32 * frequency).cos(),
+                            speed,
+                        ),
+                        _ => (0.0, 3.0 * config.gravity),
+                    };
+                    let drift = sway + config.drift + air[0];
+                    let gravity = fall + air[1];
+                    let [vx, vy] = particle.velocity;
+                    particle.position[0] += vx + drift;
+                    particle.position[1] += vy + gravity;
+                    particle.velocity = [vx * config.decay, vy * config.decay];
+
+                    let [x, y] = &mut particle.position;
+                    let [vx, vy] = &mut particle.velocity;
+                    let restitution = config.restitution;
+                    let falling = *vy + gravity > 0.0;
+                    let piles = !matches!(particle.kind, Kind::Spark);
+                    if !config
+                        .edges
+                        .sides(x, vx, drift, width, EDGE_MARGIN, restitution)
+                    {
+                        particle.lifetime = particle.age;
+                    } else if config.pile && falling && piles {
+                        land(&mut self.pile, particle, config.size, height, self.tick);
+                    } else if !config
+                        .floor
+                        .floor(y, vy, gravity, height, EDGE_MARGIN, restitution)
+                    {
+                        particle.lifetime = particle.age;
+                    }
+                }
+                Kind::Landed { .. } => {}
+                Kind::Swept { .. } => {
+                    let config = &self.config;
+                    let floor = height - particle.extent(config.size) * 0.5;
+                    let [vx, vy] = particle.velocity;
+                    particle.position[0] += vx;
+                    particle.position[1] =
+                        (particle.position[1] + vy + 3.0 * config.gravity).min(floor);
+                    particle.velocity[1] = vy * config.decay;
+                    if particle.position[0] > width + EDGE_MARGIN {
+                        particle.lifetime = particle.age;
+                    }
+                }
+            }
+        }
+        self.particles.retain(|p| p.age < p.lifetime);
+        if self.config.pile {
+            self.clear_pile();
+        }
+
+        for ribbon in &mut self.ribbons {
+            let air = self.wind.at(ribbon.points[0]);
+            ribbon.step(&self.config, self.resolution, air);
+        }
+        self.ribbons.retain(|r| r.age < r.lifetime);
+
+        for (position, color) in trails {
+            let particle = self.particle(Kind::Spark, position, color);
+            self.particles.push(Particle {
+                velocity: [
+                    self.rng.random_range(-0.6..0.6),
+                    self.rng.random_range(0.0..1.0),
+                ],
+                lifetime: TRAIL_TICKS,
+                ..particle
+            });
+        }
+        for (position, color, sparks, speed) in explosions {
+            for _ in 0..sparks {
+                let angle = self.rng.random_range(0.0..std::f32::consts::TAU);
+                let speed = speed * self.rng.random_range(0.3..1.0);
+                let color = jitter_color(color, self.config.color_variation, &mut self.rng);
+                let lifetime = self.config.ticks as f32 * self.rng.random_range(0.3..0.5);
+                let particle = self.particle(Kind::Spark, position, color);
+                self.particles.push(Particle {
+                    velocity: [angle.cos() * speed, angle.sin() * speed],
+                    lifetime: (lifetime as u32).max(1),
+                    ..particle
+                });
+            }
+        }
+    }
+
+    /// Clears the pile once it has been there for `pile_timeout`, and moves
+    /// the broom along while it is swept away.
+    fn clear_pile(&mut self) {
+        let timeout = (self.config.pile_timeout * TICK_RATE) as u32;
+        let expired = self
+            .pile
+            .started
+            .is_some_and(|started| self.tick >= started + timeout);
+        if expired && self.pile.broom.is_none() {
+            match self.config.pile_clear {
+                PileClear::Fade => {
+                    for particle in &mut self.particles {
+                        if let Kind::Landed { fading, .. } = &mut particle.kind
+                            && !*fading
+                        {
+                            *fading = true;
+                            particle.age = 0;
+                            particle.lifetime = PILE_FADE_TICKS;
+                        }
+                    }
+                    self.pile.clear();
+                    self.pile.started = None;
+                }
+                PileClear::Sweep => self.pile.broom = Some(0.0),
+            }
+        }
+
+        let Some(broom) = self.pile.broom else {
+            return;
+        };
+        let broom = broom + BROOM_SPEED;
+        self.pile.clear_until(broom);
+        for particle in &mut self.particles {
+            if let Kind::Landed { alpha, .. } = particle.kind
+                && particle.position[0] < broom
+            {
+                particle.kind = Kind::Swept { alpha };
+                particle.velocity = [
+                    BROOM_SPEED * self.rng.random_range(1.0..1.5),
+                    -self.rng.random_range(2.0..6.0),
+                ];
+                particle.spin = self.rng.random_range(-9.0..9.0);
+            }
+        }
+        if broom > self.resolution[0] {
+            self.pile.broom = None;
+            self.pile.started = None;
+        } else {
+            self.pile.broom = Some(broom);
+        }
+    }
+
+    fn fire(&mut self, emitter: &Emitter) {
+        let (origin, offset) = emitter.origin.anchor();
+        let x = origin[0] * self.resolution[0] + offset[0];
+        let y = origin[1] * self.resolution[1] + offset[1];
+        let position = [x, y];
+
+        match emitter.payload {
+            Payload::Confetti => {
+                for _ in 0..emitter.count {
+                    // Same launch distribution as canvas-confetti
+                    let angle = self.launch_angle(emitter);
+                    let speed = emitter.start_velocity * self.rng.random_range(0.5..1.5);
+                    let color = self.palette_color();
+                    let particle = self.particle(Kind::Confetti, position, color);
+                    let lifetime =
+                        self.config.ticks as f32 * self.config.lifetime.sample(&mut self.rng);
+                    let delay = self.config.spawn_delay.sample(&mut self.rng) * TICK_RATE;
+                    self.particles.push(Particle {
+                        velocity: [angle.cos() * speed, -angle.sin() * speed],
+                        lifetime: (lifetime as u32).max(1),
+                        delay: delay as u32,
+                        ..particle
+                    });
+                }
+                for _ in 0..self.config.ribbons {
+                    let angle = self.launch_angle(emitter);
+                    let speed = emitter.start_velocity * self.rng.random_range(0.5..1.5);
+                    let ribbon = Ribbon {
+                        points: [position; RIBBON_POINTS],
+                        previous: [position; RIBBON_POINTS],
+                        velocity: [angle.cos() * speed, -angle.sin() * speed],
+                        color: self.palette_color(),
+                        twist: self.rng.random_range(0.3..0.7),
+                        spin: self.rng.random_range(0.1..0.25),
+                        phase: self.rng.random_range(0.0..std::f32::consts::TAU),
+                        age: 0,
+                        lifetime: self.config.ticks,
+                    };
+                    self.ribbons.push(ribbon);
+                }
+            }
+            Payload::Stream { .. } => self.streams.push((*emitter, 0.0)),
+            Payload::Rocket => {
+                // Fast enough to come to a halt in the upper part of the screen
+                let height = self.rng.random_range(0.55..0.8) * y;
+                let rise = (2.0 * ROCKET_GRAVITY * height).sqrt();
+                let angle = self.launch_angle(emitter);
+                let color = *self.colors.pick(&mut self.rng);
+                let kind = Kind::Rocket {
+                    sparks: emitter.count,
+                    speed: emitter.start_velocity * 0.45,
+                };
+                let particle = self.particle(kind, position, color);
+                self.particles.push(Particle {
+                    velocity: [rise / angle.tan(), -rise],
+                    lifetime: u32::MAX,
+                    ..particle
+                });
+            }
+        }
+    }
+
+    /// Spawns the particles the streams have accumulated, spread along the
+    /// top edge, while staying under each stream's particle count.
+    fn spawn_streams(&mut self) {
+        for i in 0..self.streams.len() {
+            let (emitter, budget) = self.streams[i];
+            let Payload::Stream { rate, speed, sway } = emitter.payload else {
+                continue;
+            };
+            let mut budget = budget + rate / TICK_RATE;
+            let falling = self
+                .particles
+                .iter()
+                .filter(|p| matches!(p.kind, Kind::Falling { .. }))
+                .count();
+            let room = (emitter.count as usize).saturating_sub(falling);
+            for _ in 0..(budget as usize).min(room) {
+                let kind = Kind::Falling {
+                    sway: sway * self.rng.random_range(0.5..1.5),
+                    frequency: self.rng.random_range(0.02..0.06),
+                    phase: self.rng.random_range(0.0..std::f32::consts::TAU),
+                    speed: speed * self.rng.random_range(0.7..1.3),
+                };
+                let x = self.rng.random_range(0.0..=self.resolution[0]);
+                let color = self.palette_color();
+                let particle = self.particle(kind, [x, -EDGE_MARGIN], color);
+                self.particles.push(Particle {
+                    lifetime: u32::MAX,
+                    ..particle
+                });
+            }
+            // Spawns beyond the particle count are dropped, not saved up
+            budget = budget.fract();
+            self.streams[i].1 = budget;
+        }
+    }
+
+    fn launch_angle(&mut self, emitter: &Emitter) -> f32 {
+        (emitter.angle + emitter.spread * self.rng.random_range(-0.5..0.5)).to_radians()
+    }
+
+    /// Picks a direction uniformly distributed on the unit sphere.
+    fn random_axis(&mut self) -> [f32; 3] {
+        let z: f32 = self.rng.random_range(-1.0..1.0);
+        let angle = self.rng.random_range(0.0..std::f32::consts::TAU);
+        let radius = (1.0 - z * z).sqrt();
+        [radius * angle.cos(), radius * angle.sin(), z]
+    }
+
+    fn palette_color(&mut self) -> [f32; 3] {
+        let color = *self.colors.pick(&mut self.rng);
+        jitter_color(color, self.config.color_variation, &mut self.rng)
+    }
+
+    fn particle(&mut self, kind: Kind, position: [f32; 2], color: [f32; 3]) -> Particle {
+        let mut particle = Particle {
+            kind,
+            position,
+            velocity: [0.0, 0.0],
+            color,
+            // Rockets and their sparks are small round points of light that
+            // always face the viewer
+            shape: Shape::Circle.id(),
+            axis: [0.0, 0.0, 1.0],
+            spin: 0.0,
+            tumble: 0.0,
+            size: 0.5,
+            rotation: 0.0,
+            angular_velocity: 0.0,
+            opacity: 1.0,
+            delay: 0,
+            age: 0,
+            lifetime: 1,
+            // Kept small, the shader's hash loses precision on large values
+            seed: self.rng.random_range(0..0x10000),
+        };
+        // Everything else tumbles like paper and takes the per-particle
+        // attributes of the config
+        if let Kind::Confetti | Kind::Falling { .. } = kind {
+            let spin = match kind {
+                Kind::Falling { .. } => self.rng.random_range(1.0..4.0),
+                _ => self.rng.random_range(3.0..9.0),
+            };
+            let direction = if self.rng.random_bool(0.5) { 1.0 } else { -1.0 };
+            particle.shape = *self.shapes.pick(&mut self.rng);
+            particle.axis = self.random_axis();
+            particle.spin = spin * direction;
+            let config = &self.config;
+            particle.size = config.size_scale.sample(&mut self.rng);
+            particle.rotation = config.rotation.sample(&mut self.rng).to_radians();
+            particle.angular_velocity =
+                config.angular_velocity.sample(&mut self.rng).to_radians() / TICK_RATE;
+            particle.opacity = config.opacity.sample(&mut self.rng);
+        }
+        particle
+    }
+}
+
+/// Lets a falling particle settle once it reaches the pile, which is at least
+/// the bottom edge.
+fn land(pile: &mut Pile, particle: &mut Particle, scale: f32, height: f32, tick: u32) {
+    let extent = particle.extent(scale);
+    let [x, y] = particle.position;
+    if y + extent * 0.5 < height - pile.height(x, extent) {
+        return;
+    }
+    let (x, rest) = pile.land(x, extent);
+    particle.position = [x, height - rest - extent * STACKING];
+    particle.velocity = [0.0, 0.0];
+    particle.spin = 0.0;
+    particle.angular_velocity = 0.0;
+    particle.kind = Kind::Landed {
+        alpha: particle.alpha(),
+        fading: false,
+    };
+    particle.lifetime = u32::MAX;
+    pile.started.get_or_insert(tick);
+}
+
+/// Spreads a palette color in OKLab, so particles sharing a palette entry
+/// differ slightly in lightness and chroma.
+fn jitter_color(rgb: [f32; 3], amount: f32, rng: &mut impl Rng) -> [f32; 3] {
+    if amount <= 0.0 {
+        return rgb;
+    }
+    let [l, a, b] = color::srgb_to_oklab(rgb);
+    let l = (l + amount * rng.random_range(-0.12..0.12)).clamp(0.0, 1.0);
+    let chroma = 1.0 + amount * rng.random_range(-0.35..0.35);
+    color::oklab_to_srgb([l, a * chroma, b * chroma])
+}
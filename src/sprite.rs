@@ -0,0 +1,84 @@
+use std::{path::PathBuf, process::Command, str::FromStr};
+
+use image::{ImageFormat, ImageReader, RgbaImage};
+use serde::Deserialize;
+
+use crate::{config, weighted};
+
+/// An image file drawn as particles, written as `path` or `path:weight`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Sprite {
+    pub path: PathBuf,
+    /// How often the sprite is picked relative to the other particle kinds.
+    pub weight: u32,
+}
+
+impl Sprite {
+    /// Loads a PNG or JPEG image, or renders an SVG with its longer side
+    /// `size` pixels long.
+    pub fn load(&self, size: u32) -> Result<RgbaImage, String> {
+        let path = config::expand_home(&self.path);
+        let is_svg = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| matches!(ext.to_lowercase().as_str(), "svg" | "svgz"));
+        if is_svg {
+            return render_svg(&path, size);
+        }
+        Ok(ImageReader::open(&path)
+            .and_then(|reader| reader.with_guessed_format())
+            .map_err(|e| format!("{}: {}", path.display(), e))?
+            .decode()
+            .map_err(|e| format!("{}: {}", path.display(), e))?
+            .to_rgba8())
+    }
+}
+
+impl FromStr for Sprite {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, weight) = weighted::split_weight(s)?;
+        if path.is_empty() {
+            return Err(format!("Missing path in sprite '{}'", s));
+        }
+        Ok(Self {
+            path: PathBuf::from(path),
+            weight,
+        })
+    }
+}
+
+impl TryFrom<String> for Sprite {
+    type Error = String;
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// Rasterizes an SVG with librsvg's `rsvg-convert`, keeping its aspect ratio.
+fn render_svg(path: &std::path::Path, size: u32) -> Result<RgbaImage, String> {
+    let output = Command::new("rsvg-convert")
+        .arg("--format=png")
+        .arg("--keep-aspect-ratio")
+        .args(["--width", &size.to_string(), "--height", &size.to_string()])
+        .arg(path)
+        .output()
+        .map_err(|e| {
+            format!(
+                "{}: failed to run rsvg-convert, which renders SVG sprites: {}",
+                path.display(),
+                e
+            )
+        })?;
+    if !output.status.success() {
+        return Err(format!(
+            "{}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    image::load_from_memory_with_format(&output.stdout, ImageFormat::Png)
+        .map(|image| image.to_rgba8())
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
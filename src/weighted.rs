@@ -0,0 +1,105 @@
+use rand::{
+    Rng,
+    distr::{Distribution, weighted::WeightedIndex},
+};
+
+/// Largest weight a list entry can have.
+pub const MAX_WEIGHT: u32 = 1000;
+
+/// Splits the optional `:weight` suffix off a list entry like `star:3`. Only
+/// a suffix that looks like a number is a weight, so other colons stay part
+/// of the name and a lone `:` is a name as well.
+pub fn split_weight(entry: &str) -> Result<(&str, u32), String> {
+    let entry = entry.trim();
+    let numeric = |w: &str| {
+        !w.is_empty()
+            && w.chars()
+                .all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '.'))
+    };
+    match entry.rsplit_once(':') {
+        Some((name, weight)) if !name.is_empty() && numeric(weight.trim()) => {
+            let weight = weight.trim();
+            let parsed = weight
+                .parse::<u32>()
+                .ok()
+                .filter(|w| (1..=MAX_WEIGHT).contains(w))
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid weight '{}' in '{}': expected a whole number from 1 to {}",
+                        weight, entry, MAX_WEIGHT
+                    )
+                })?;
+            Ok((name.trim_end(), parsed))
+        }
+        _ => Ok((entry, 1)),
+    }
+}
+
+/// Items picked at random, each as often as its weight says relative to the
+/// others.
+#[derive(Debug, Clone)]
+pub struct Weighted<T> {
+    items: Vec<T>,
+    index: WeightedIndex<u32>,
+}
+
+impl<T> Weighted<T> {
+    /// Returns `None` if there are no items.
+    pub fn new(entries: impl IntoIterator<Item = (T, u32)>) -> Option<Self> {
+        let (items, weights): (Vec<T>, Vec<u32>) = entries.into_iter().unzip();
+        let index = WeightedIndex::new(weights).ok()?;
+        Some(Self { items, index })
+    }
+
+    pub fn pick(&self, rng: &mut impl Rng) -> &T {
+        &self.items[self.index.sample(rng)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_numeric_suffix() {
+        assert_eq!(split_weight("star:3"), Ok(("star", 3)));
+        assert_eq!(split_weight(" star : 3 "), Ok(("star", 3)));
+        assert_eq!(split_weight("star"), Ok(("star", 1)));
+    }
+
+    #[test]
+    fn keeps_other_colons_in_the_name() {
+        assert_eq!(split_weight(":"), Ok((":", 1)));
+        assert_eq!(split_weight("::2"), Ok((":", 2)));
+        assert_eq!(split_weight("C:/logo.png"), Ok(("C:/logo.png", 1)));
+        assert_eq!(split_weight("a:b:4"), Ok(("a:b", 4)));
+    }
+
+    #[test]
+    fn rejects_bad_weights() {
+        let error = |w: &str| {
+            format!(
+                "Invalid weight '{}' in 'star:{}': expected a whole number from 1 to 1000",
+                w, w
+            )
+        };
+        assert_eq!(split_weight("star:0"), Err(error("0")));
+        assert_eq!(split_weight("star:-2"), Err(error("-2")));
+        assert_eq!(split_weight("star:1.5"), Err(error("1.5")));
+        assert_eq!(split_weight("star:1001"), Err(error("1001")));
+        assert_eq!(split_weight("star:4000000000"), Err(error("4000000000")));
+    }
+
+    #[test]
+    fn picks_by_weight() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        assert!(Weighted::<u8>::new([]).is_none());
+        let weighted = Weighted::new([('a', 1), ('b', 3)]).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        let b = (0..4000)
+            .filter(|_| *weighted.pick(&mut rng) == 'b')
+            .count();
+        assert!((2800..3200).contains(&b), "picked 'b' {} times", b);
+    }
+}
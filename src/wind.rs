@@ -0,0 +1,113 @@
+use crate::config::Config;
+
+/// Gusts come and go about this many times per second.
+const GUST_RATE: f32 = 0.35;
+/// How fast the turbulence changes, in noise cells per second.
+const TURBULENCE_RATE: f32 = 0.25;
+/// Step for the finite differences of the curl, in noise cells.
+const CURL_STEP: f32 = 0.05;
+
+/// Moving air that carries light particles along, like gravity and drift it
+/// moves them at a constant speed: a steady wind, gusts that come and go in
+/// the wind's direction, and swirls from a curl noise field.
+pub struct Wind {
+    seed: u32,
+    /// Steady wind in pixels per tick.
+    steady: [f32; 2],
+    gusts: f32,
+    turbulence: f32,
+    /// Size of a noise cell in pixels.
+    scale: f32,
+    /// Wind including the current gust.
+    current: [f32; 2],
+    /// Time coordinate of the noise field.
+    time: f32,
+}
+
+impl Wind {
+    pub fn new(config: &Config, seed: u32) -> Self {
+        Self {
+            seed,
+            steady: config.wind,
+            gusts: config.gusts,
+            turbulence: config.turbulence,
+            scale: config.turbulence_scale,
+            current: config.wind,
+            time: 0.0,
+        }
+    }
+
+    /// Moves the gusts and turbulence on to `time` seconds after the start.
+    pub fn update(&mut self, time: f32) {
+        let [x, y] = self.steady;
+        let length = (x * x + y * y).sqrt();
+        // Without a steady wind, gusts blow to the right
+        let direction = if length > f32::EPSILON {
+            [x / length, y / length]
+        } else {
+            [1.0, 0.0]
+        };
+        // Calm about half of the time, rising quickly into a gust otherwise
+        let gust = (noise(self.seed ^ 0x9e37_79b9, [time * GUST_RATE, 0.0, 0.0]) * 2.0)
+            .clamp(0.0, 1.0)
+            * self.gusts;
+        self.current = [x + direction[0] * gust, y + direction[1] * gust];
+        self.time = time * TURBULENCE_RATE;
+    }
+
+    /// Movement of the air at `position` in pixels per tick.
+    pub fn at(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        if self.turbulence <= 0.0 {
+            return self.current;
+        }
+        // The curl of a noise field swirls without sources or sinks, so
+        // particles don't bunch up in some places
+        let potential = |dx: f32, dy: f32| {
+            noise(
+                self.seed,
+                [x / self.scale + dx, y / self.scale + dy, self.time],
+            )
+        };
+        let dx = (potential(CURL_STEP, 0.0) - potential(-CURL_STEP, 0.0)) / (2.0 * CURL_STEP);
+        let dy = (potential(0.0, CURL_STEP) - potential(0.0, -CURL_STEP)) / (2.0 * CURL_STEP);
+        [
+            self.current[0] + dy * self.turbulence,
+            self.current[1] - dx * self.turbulence,
+        ]
+    }
+}
+
+/// Smooth value noise between -1 and 1.
+fn noise(seed: u32, point: [f32; 3]) -> f32 {
+    let cell = point.map(f32::floor);
+    let [fx, fy, fz] = [0, 1, 2].map(|i| point[i] - cell[i]);
+    let [cx, cy, cz] = cell.map(|c| c as i32);
+    // Quintic fade, so the curl has no creases at the cell borders
+    let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let corner = |x: i32, y: i32, z: i32| hash(seed, cx + x, cy + y, cz + z);
+
+    let [u, v, w] = [fade(fx), fade(fy), fade(fz)];
+    let plane = |z: i32| {
+        lerp(
+            lerp(corner(0, 0, z), corner(1, 0, z), u),
+            lerp(corner(0, 1, z), corner(1, 1, z), u),
+            v,
+        )
+    };
+    lerp(plane(0), plane(1), w)
+}
+
+/// Random value between -1 and 1 for a lattice point.
+fn hash(seed: u32, x: i32, y: i32, z: i32) -> f32 {
+    let mut h = seed
+        ^ (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    h as f32 / u32::MAX as f32 * 2.0 - 1.0
+}